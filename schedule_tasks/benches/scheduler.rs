@@ -0,0 +1,70 @@
+use criterion::{
+    black_box,
+    criterion_group,
+    criterion_main,
+    BenchmarkId,
+    Criterion,
+};
+use schedule_tasks::input::parsers::parse_job;
+use schedule_tasks::scheduler::build_scheduler;
+
+// Generates a job in the .tasks.in format where every task depends on up to three of the
+// hundred tasks before it. A fixed linear congruential generator keeps the job reproducible.
+fn generate_job(number_of_tasks: usize) -> String {
+    let mut seed: u64 = 42;
+    let mut next_random = move || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (seed >> 33) as usize
+    };
+
+    let mut job = String::new();
+    for index in 0..number_of_tasks {
+        job.push_str(&format!("T{}({})", index, next_random() % 10 + 1));
+        if index > 0 {
+            let dependencies: Vec<String> = (0..next_random() % 3 + 1)
+                .map(|_| format!("T{}", index - 1 - next_random() % index.min(100)))
+                .collect();
+            job.push_str(&format!(" after [{}]", dependencies.join(", ")));
+        }
+        job.push('\n');
+    }
+    job
+}
+
+fn schedule_large_jobs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("schedule_tasks");
+    group.sample_size(10);
+
+    for number_of_tasks in [1_000, 10_000, 50_000] {
+        let job = generate_job(number_of_tasks);
+        let tasks = parse_job(&job).expect("generated job should parse").tasks;
+
+        group.bench_with_input(BenchmarkId::from_parameter(number_of_tasks), &tasks, |b, tasks| {
+            b.iter(|| {
+                let mut scheduler = build_scheduler(black_box(tasks.clone()));
+                scheduler.schedule_tasks().expect("generated job should schedule");
+                scheduler
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn parse_large_jobs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_job");
+    group.sample_size(10);
+
+    for number_of_tasks in [1_000, 10_000, 50_000] {
+        let job = generate_job(number_of_tasks);
+
+        group.bench_with_input(BenchmarkId::from_parameter(number_of_tasks), &job, |b, job| {
+            b.iter(|| parse_job(black_box(job)).expect("generated job should parse"))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, parse_large_jobs, schedule_large_jobs);
+criterion_main!(benches);
@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::error::ScheduleError;
+use crate::policy::Policy;
+use crate::scheduler::{
+    build_job_scheduler,
+    Scheduler,
+};
+use crate::task::{
+    Dependency,
+    Duration,
+    Job,
+    Task,
+    TimeMoment,
+};
+use crate::time::TimeScale;
+
+
+// A task in both versions of a job with another duration, other dependencies or another command. Unchanged properties
+// are None.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangedTask {
+    pub name: String,
+    pub duration: Option<(Duration, Duration)>,
+    pub dependencies: Option<(Vec<Dependency>, Vec<Dependency>)>,
+    pub command: Option<(Option<String>, Option<String>)>,
+}
+
+// The difference between the tasks of two versions of a job, each list in the order of the file it comes from.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<ChangedTask>,
+}
+
+impl TaskDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    // one line per task: Added: E, Removed: F, Changed: B duration 2 -> 3, Changed: C dependencies [A] -> [A, B],
+    // Changed: D command "make" -> none
+    pub fn print(&self, time_scale: TimeScale) -> Vec<String> {
+        let print_dependencies = |dependencies: &[Dependency]| format!("[{}]", dependencies
+            .iter()
+            .map(|dependency| dependency.print(time_scale))
+            .collect::<Vec<String>>()
+            .join(", "));
+        let print_command = |command: &Option<String>| command.as_ref().map_or("none".to_string(), |command| format!("{:?}", command));
+
+        let added = self.added.iter().map(|name| format!("Added: {}", name));
+        let removed = self.removed.iter().map(|name| format!("Removed: {}", name));
+        let changed = self.changed.iter().flat_map(|task| {
+            let duration = task.duration.map(|(from, to)| {
+                format!("Changed: {} duration {} -> {}", task.name, time_scale.format(from), time_scale.format(to))
+            });
+            let dependencies = task.dependencies.as_ref().map(|(from, to)| {
+                format!("Changed: {} dependencies {} -> {}", task.name, print_dependencies(from), print_dependencies(to))
+            });
+            let command = task.command.as_ref().map(|(from, to)| {
+                format!("Changed: {} command {} -> {}", task.name, print_command(from), print_command(to))
+            });
+            duration.into_iter().chain(dependencies).chain(command)
+        });
+
+        added.chain(removed).chain(changed).collect()
+    }
+}
+
+// A task that starts at another time in the new version of a job: the start-time before and after.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MovedTask {
+    pub name: String,
+    pub start_time: (TimeMoment, TimeMoment),
+}
+
+// The difference between the schedules of two versions of a job: the changed tasks, the tasks that start at another
+// time, and the minimum duration, critical path and maximum parallelism before and after.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleDiff {
+    pub tasks: TaskDiff,
+    pub moved: Vec<MovedTask>,
+    pub makespan: (TimeMoment, TimeMoment),
+    pub critical_path: (Vec<String>, Vec<String>),
+    pub max_parallelism: (usize, usize),
+    pub time_scale: TimeScale,
+}
+
+impl ScheduleDiff {
+    pub fn critical_path_changed(&self) -> bool {
+        self.critical_path.0 != self.critical_path.1
+    }
+
+    pub fn print(&self) -> String {
+        let time_scale = self.time_scale;
+        let mut lines = self.tasks.print(time_scale);
+        lines.extend(self.moved.iter().map(|task| {
+            let (from, to) = task.start_time;
+            let critical = if self.critical_path.1.contains(&task.name) { " (critical)" } else { "" };
+            format!("Moved: {} {} -> {} ({}){}", task.name, time_scale.format(from), time_scale.format(to),
+                    time_scale.format_signed(to as isize - from as isize), critical)
+        }));
+
+        let (from, to) = self.makespan;
+        lines.push(if from == to {
+            format!("Minimum: {}", time_scale.format(to))
+        } else {
+            format!("Minimum: {} -> {}", time_scale.format(from), time_scale.format(to))
+        });
+        let (from, to) = self.max_parallelism;
+        lines.push(if from == to { format!("Parallelism: {}", to) } else { format!("Parallelism: {} -> {}", from, to) });
+        // a changed critical path gets its own line, as the first thing to look at
+        let (from, to) = &self.critical_path;
+        lines.push(if self.critical_path_changed() {
+            format!("Critical path changed: {} => {}", from.join("->"), to.join("->"))
+        } else {
+            format!("Critical: {}", to.join("->"))
+        });
+
+        lines.join("\n")
+    }
+}
+
+// The tasks of two versions of a job compared in the same ticks: a job in whole time units next to one with finer
+// ticks counts in seconds, as in a job file with included files.
+pub fn diff_jobs(old: &Job, new: &Job) -> TaskDiff {
+    let in_ticks_of = |job: &Job, time_scale: TimeScale| -> Vec<Task> {
+        let mut tasks = job.tasks.clone();
+        tasks.iter_mut().for_each(|task| task.rescale(time_scale.ticks_per_unit(), job.time_scale.ticks_per_unit()));
+        tasks
+    };
+    if old.time_scale == new.time_scale {
+        diff_tasks(&old.tasks, &new.tasks)
+    } else {
+        let time_scale = old.time_scale.max(new.time_scale);
+        diff_tasks(&in_ticks_of(old, time_scale), &in_ticks_of(new, time_scale))
+    }
+}
+
+// Compares the schedules of two versions of a job, made by the schedulers, with the difference between their tasks.
+pub fn diff_schedules(old: &Scheduler, new: &Scheduler, tasks: TaskDiff) -> ScheduleDiff {
+    let time_scale = old.time_scale.max(new.time_scale);
+    let ticks = |scheduler: &Scheduler| if scheduler.time_scale == TimeScale::Units && time_scale != TimeScale::Units { 1000 } else { 1 };
+    let (old_ticks, new_ticks) = (ticks(old), ticks(new));
+
+    let old_start_times: HashMap<&str, TimeMoment> = old.scheduled_tasks
+        .iter()
+        .map(|task| (task.name.as_str(), task.start_time * old_ticks))
+        .collect();
+    let moved = new.scheduled_tasks
+        .iter()
+        .filter_map(|task| match old_start_times.get(task.name.as_str()) {
+            Some(&start_time) if start_time != task.start_time * new_ticks => Some(MovedTask {
+                name: task.name.clone(),
+                start_time: (start_time, task.start_time * new_ticks),
+            }),
+            _ => None,
+        })
+        .collect();
+    let critical_path = |scheduler: &Scheduler| scheduler.critical_path_names().into_iter().rev().map(str::to_string).collect();
+
+    ScheduleDiff {
+        tasks,
+        moved,
+        makespan: (old.last_task.end_time * old_ticks, new.last_task.end_time * new_ticks),
+        critical_path: (critical_path(old), critical_path(new)),
+        max_parallelism: (old.max_parallelism, new.max_parallelism),
+        time_scale,
+    }
+}
+
+// Schedules two versions of a job with the same policy, each within the capacities of its resources, and compares them.
+pub fn diff_job_schedules(old: Job, new: Job, policy: Policy) -> Result<ScheduleDiff, ScheduleError> {
+    let tasks = diff_jobs(&old, &new);
+    let schedule = |job: Job| -> Result<Scheduler, ScheduleError> {
+        let mut scheduler = build_job_scheduler(job);
+        scheduler.policy = policy;
+        scheduler.schedule()?;
+        Ok(scheduler)
+    };
+
+    Ok(diff_schedules(&schedule(old)?, &schedule(new)?, tasks))
+}
+
+// Tasks are matched by name, the first of tasks with the same name.
+pub fn diff_tasks(old: &[Task], new: &[Task]) -> TaskDiff {
+    let mut old_tasks: HashMap<&str, &Task> = HashMap::with_capacity(old.len());
+    for task in old {
+        old_tasks.entry(task.name.as_str()).or_insert(task);
+    }
+    let mut new_tasks: HashMap<&str, &Task> = HashMap::with_capacity(new.len());
+    for task in new {
+        new_tasks.entry(task.name.as_str()).or_insert(task);
+    }
+
+    let mut diff = TaskDiff::default();
+    for task in new {
+        match old_tasks.get(task.name.as_str()) {
+            None => diff.added.push(task.name.clone()),
+            Some(old_task) => {
+                let changed = ChangedTask {
+                    name: task.name.clone(),
+                    duration: (old_task.duration != task.duration).then_some((old_task.duration, task.duration)),
+                    dependencies: (old_task.dependencies != task.dependencies).then(|| (old_task.dependencies.clone(), task.dependencies.clone())),
+                    command: (old_task.command != task.command).then(|| (old_task.command.clone(), task.command.clone())),
+                };
+                if changed.duration.is_some() || changed.dependencies.is_some() || changed.command.is_some() {
+                    diff.changed.push(changed);
+                }
+            }
+        }
+    }
+    diff.removed = old
+        .iter()
+        .filter(|task| !new_tasks.contains_key(task.name.as_str()))
+        .map(|task| task.name.clone())
+        .collect();
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::parsers::parse_job;
+
+    #[test]
+    fn diff_tasks_by_name() {
+        let old = vec![
+            Task::new("A", 1, vec![]),
+            Task::new("B", 2, vec!["A".to_string()]),
+            Task::new("C", 1, vec!["A".to_string()]),
+            Task::new("F", 1, vec![]),
+            Task::new("G", 1, vec![]).running("make"),
+        ];
+        let new = vec![
+            Task::new("A", 1, vec![]),
+            Task::new("B", 3, vec!["A".to_string()]),
+            Task::new("C", 1, vec!["A".to_string(), "B".to_string()]),
+            Task::new("E", 1, vec![]),
+            Task::new("G", 1, vec![]).running("make \"all\""),
+        ];
+
+        let diff = diff_tasks(&old, &new);
+        assert_eq!(diff.added, vec!["E"]);
+        assert_eq!(diff.removed, vec!["F"]);
+        assert_eq!(diff.print(TimeScale::Units), vec![
+            "Added: E",
+            "Removed: F",
+            "Changed: B duration 2 -> 3",
+            "Changed: C dependencies [A] -> [A, B]",
+            r#"Changed: G command "make" -> "make \"all\"""#,
+        ]);
+        assert!(diff_tasks(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn diff_schedules_of_two_jobs() {
+        let old = parse_job("A(1)\nB(1) after [A]\nC(1)\nD(1) after [B, C]\n").unwrap();
+        let new = parse_job("A(1)\nB(1) after [A]\nC(2.5)\nD(1) after [B, C]\nE(1)\n").unwrap();
+
+        let diff = diff_job_schedules(old, new, Policy::FileOrder).unwrap();
+        assert_eq!(diff.time_scale, TimeScale::Thousandths);
+        assert_eq!(diff.tasks.changed, vec![ChangedTask { name: "C".to_string(), duration: Some((1000, 2500)), ..Default::default() }]);
+        assert_eq!(diff.moved, vec![MovedTask { name: "D".to_string(), start_time: (2000, 2500) }]);
+        assert_eq!(diff.print(), r#"Added: E
+Changed: C duration 1 -> 2.5
+Moved: D 2 -> 2.5 (+0.5) (critical)
+Minimum: 3 -> 3.5
+Parallelism: 2 -> 3
+Critical path changed: A->B->D => C->D"#);
+    }
+
+    #[test]
+    fn diff_schedules_with_a_policy() {
+        let old = parse_job("capacity [cpu:1]\nA(1) uses [cpu:1]\nB(3) uses [cpu:1]\n").unwrap();
+        let new = parse_job("capacity [cpu:1]\nA(1) uses [cpu:1]\nB(2) uses [cpu:1]\n").unwrap();
+
+        assert_eq!(diff_job_schedules(old.clone(), new.clone(), Policy::FileOrder).unwrap().moved, vec![]);
+        // the longest task goes first, so A starts when the shorter B ends
+        let diff = diff_job_schedules(old, new, Policy::LongestProcessingTime).unwrap();
+        assert_eq!(diff.moved, vec![MovedTask { name: "A".to_string(), start_time: (3, 2) }]);
+    }
+}
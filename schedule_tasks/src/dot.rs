@@ -0,0 +1,175 @@
+use std::collections::{
+    BTreeMap,
+    HashSet,
+};
+use crate::scheduler::Scheduler;
+use crate::task::{
+    DependencyKind,
+    TimeMoment,
+};
+
+
+// A DOT quoted string, as a task-name made in code can hold any character.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// The dependency graph in the Graphviz DOT language, edges pointing from a dependency to its dependent.
+// Critical edges are drawn bold and red, critical waits for resources dashed. With clusters, tasks starting at the
+// same time are grouped in a box.
+pub fn print_dot(scheduler: &Scheduler, clusters: bool) -> String {
+    let position_in_schedule = scheduler.graph.topological_positions();
+    let mut critical_edges: HashSet<(usize, usize)> = scheduler.critical_edges().into_iter().collect();
+
+    let node = |position: usize| {
+        let task = &scheduler.scheduled_tasks[position];
+        let critical = scheduler.timings.get(position).is_some_and(|timing| timing.total_float == 0);
+        let label = format!("{} ({})", task.name, scheduler.time_scale.format(task.duration));
+        format!("{} [label={}{}];", quote(&task.name), quote(&label), if critical { ", color=red" } else { "" })
+    };
+
+    let mut lines: Vec<String> = vec![
+        "digraph job {".to_string(),
+        "  rankdir=LR;".to_string(),
+        "  node [shape=box];".to_string(),
+    ];
+
+    if clusters {
+        let mut positions_by_start_time: BTreeMap<TimeMoment, Vec<usize>> = BTreeMap::new();
+        for (position, task) in scheduler.scheduled_tasks.iter().enumerate() {
+            positions_by_start_time.entry(task.start_time).or_default().push(position);
+        }
+        for (start_time, positions) in positions_by_start_time {
+            lines.push(format!("  subgraph cluster_{} {{", start_time));
+            lines.push(format!("    label=\"start {}\";", scheduler.time_scale.format(start_time)));
+            lines.extend(positions.into_iter().map(|position| format!("    {}", node(position))));
+            lines.push("  }".to_string());
+        }
+    } else {
+        lines.extend((0..scheduler.scheduled_tasks.len()).map(|position| format!("  {}", node(position))));
+    }
+
+    // other dependency types than finish-to-start, and lags, are written on the edge as in the input file
+    for (position, task) in scheduler.scheduled_tasks.iter().enumerate() {
+        for dependency in &task.dependencies {
+            let dependency_position = match dependency.id {
+                Some(id) => position_in_schedule[id.index()],
+                None => continue,
+            };
+            let mut attributes = vec![];
+            if dependency.kind != DependencyKind::FinishToStart || dependency.lag != 0 {
+                attributes.push(format!("label={}", quote(&dependency.print(scheduler.time_scale))));
+            }
+            if critical_edges.remove(&(dependency_position, position)) {
+                attributes.push("color=red, penwidth=2".to_string());
+            }
+            let attributes = if attributes.is_empty() { String::new() } else { format!(" [{}]", attributes.join(", ")) };
+            lines.push(format!("  {} -> {}{};", quote(&scheduler.scheduled_tasks[dependency_position].name), quote(&task.name), attributes));
+        }
+    }
+
+    // the critical edges left are resource dependencies
+    let mut resource_edges: Vec<(usize, usize)> = critical_edges.into_iter().collect();
+    resource_edges.sort_unstable();
+    for (dependency_position, position) in resource_edges {
+        lines.push(format!("  {} -> {} [style=dashed, color=red, penwidth=2];", quote(&scheduler.scheduled_tasks[dependency_position].name), quote(&scheduler.scheduled_tasks[position].name)));
+    }
+
+    lines.push("}".to_string());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::build_resource_pool;
+    use crate::scheduler::build_scheduler;
+    use crate::task::{
+        Dependency,
+        Task,
+    };
+
+    #[test]
+    fn print_dot_graph() {
+        let a = Task::new("A", 1, vec![]);
+        let b = Task::new("B", 1, vec!["A".to_string()]);
+        let c = Task::new("C", 3, vec!["A".to_string()]);
+
+        let mut scheduler = build_scheduler(vec![a, b, c]);
+        scheduler.schedule().unwrap();
+
+        assert_eq!(print_dot(&scheduler, false), r#"digraph job {
+  rankdir=LR;
+  node [shape=box];
+  "A" [label="A (1)", color=red];
+  "B" [label="B (1)"];
+  "C" [label="C (3)", color=red];
+  "A" -> "B";
+  "A" -> "C" [color=red, penwidth=2];
+}"#);
+    }
+
+    #[test]
+    fn print_dot_graph_with_quotes_in_names() {
+        let a = Task::new(r#"say "hi""#, 1, vec![]);
+        let b = Task::new(r"C:\build", 1, vec![r#"say "hi""#.to_string()]);
+
+        let mut scheduler = build_scheduler(vec![a, b]);
+        scheduler.schedule().unwrap();
+
+        let dot = print_dot(&scheduler, false);
+        assert!(dot.contains(r#"  "say \"hi\"" [label="say \"hi\" (1)", color=red];"#));
+        assert!(dot.contains(r#"  "say \"hi\"" -> "C:\\build" [color=red, penwidth=2];"#));
+    }
+
+    #[test]
+    fn print_dot_graph_with_dependency_types() {
+        let a = Task::new("A", 2, vec![]);
+        let b = Task::new("B", 1, vec![]).after(vec![Dependency { name: "A".to_string(), kind: DependencyKind::StartToStart, lag: 1, ..Default::default() }]);
+
+        let mut scheduler = build_scheduler(vec![a, b]);
+        scheduler.schedule().unwrap();
+
+        assert!(print_dot(&scheduler, false).contains(r#"  "A" -> "B" [label="start(A)+1", color=red, penwidth=2];"#));
+    }
+
+    #[test]
+    fn print_dot_graph_with_resource_dependencies() {
+        let a = Task::new("A", 2, vec![]).using(vec![("gpu".to_string(), 1)]);
+        let b = Task::new("B", 1, vec![]).using(vec![("gpu".to_string(), 1)]);
+
+        let mut scheduler = build_scheduler(vec![a, b]);
+        scheduler.resources = build_resource_pool(&[("gpu".to_string(), 1)]);
+        scheduler.schedule().unwrap();
+
+        assert!(print_dot(&scheduler, false).ends_with(r#"  "B" [label="B (1)", color=red];
+  "A" -> "B" [style=dashed, color=red, penwidth=2];
+}"#));
+    }
+
+    #[test]
+    fn print_dot_graph_with_clusters() {
+        let a = Task::new("A", 1, vec![]);
+        let b = Task::new("B", 1, vec!["A".to_string()]);
+        let c = Task::new("C", 3, vec!["A".to_string()]);
+
+        let mut scheduler = build_scheduler(vec![a, b, c]);
+        scheduler.schedule().unwrap();
+
+        assert_eq!(print_dot(&scheduler, true), r#"digraph job {
+  rankdir=LR;
+  node [shape=box];
+  subgraph cluster_0 {
+    label="start 0";
+    "A" [label="A (1)", color=red];
+  }
+  subgraph cluster_1 {
+    label="start 1";
+    "B" [label="B (1)"];
+    "C" [label="C (3)", color=red];
+  }
+  "A" -> "B";
+  "A" -> "C" [color=red, penwidth=2];
+}"#);
+    }
+}
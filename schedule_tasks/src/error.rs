@@ -0,0 +1,293 @@
+use std::fmt;
+use crate::task::SourceLocation;
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleError {
+    DuplicateTaskName {
+        name: String,
+        first: Option<SourceLocation>,
+        duplicate: Option<SourceLocation>,
+    },
+    UnknownDependency {
+        task: String,
+        dependency: String,
+        location: Option<SourceLocation>,
+    },
+    SelfDependency {
+        task: String,
+        location: Option<SourceLocation>,
+    },
+    Cycle {
+        tasks: Vec<String>,
+        locations: Vec<Option<SourceLocation>>,
+    },
+    CapacityExceeded {
+        task: String,
+        resource: String,
+        required: usize,
+        capacity: usize,
+        location: Option<SourceLocation>,
+    },
+    DuplicateCapacity {
+        resource: String,
+        locations: Vec<Option<SourceLocation>>,
+    },
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleError::DuplicateTaskName { name, first, duplicate } => {
+                write!(f, "task name {} is not unique", name)?;
+                write_locations(f, &[first.clone(), duplicate.clone()])
+            }
+            ScheduleError::UnknownDependency { task, dependency, location } => {
+                write!(f, "task {} depends on unknown task {}", task, dependency)?;
+                write_locations(f, std::slice::from_ref(location))
+            }
+            ScheduleError::SelfDependency { task, location } => {
+                write!(f, "task {} depends on itself", task)?;
+                write_locations(f, std::slice::from_ref(location))
+            }
+            ScheduleError::Cycle { tasks, locations } => {
+                write!(f, "dependency cycle {}", tasks.join(" -> "))?;
+                if let Some(first) = tasks.first() {
+                    write!(f, " -> {}", first)?;
+                }
+                write_locations(f, locations)
+            }
+            ScheduleError::CapacityExceeded { task, resource, required, capacity, location } => {
+                write!(f, "task {} uses {} {} but the capacity is {}", task, required, resource, capacity)?;
+                write_locations(f, std::slice::from_ref(location))
+            }
+            ScheduleError::DuplicateCapacity { resource, locations } => {
+                write!(f, "capacity of {} is declared more than once", resource)?;
+                write_locations(f, locations)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+// Every error of a job at once, so a job made in code can be fixed in one pass as a job file can.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobError {
+    pub errors: Vec<ScheduleError>,
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors: Vec<String> = self.errors.iter().map(|error| error.to_string()).collect();
+        write!(f, "{}", errors.join("\n"))
+    }
+}
+
+impl std::error::Error for JobError {}
+
+// Errors loading a job file with the job files it includes: a file that cannot be read or is included again, or a
+// resource of which the files declare different capacities. Locations point at the include directive or the capacity
+// lines. Syntax errors are kept with each file instead, so those of every file are reported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncludeError {
+    Read {
+        file: String,
+        message: String,
+        location: Option<SourceLocation>,
+    },
+    Cycle {
+        files: Vec<String>,
+        location: SourceLocation,
+    },
+    CapacityConflict {
+        resource: String,
+        capacities: Vec<usize>,
+        locations: Vec<Option<SourceLocation>>,
+    },
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IncludeError::Read { file, message, location } => {
+                write!(f, "could not read {}: {}", file, message)?;
+                write_locations(f, std::slice::from_ref(location))
+            }
+            IncludeError::Cycle { files, location } => write!(f, "include cycle {} ({})", files.join(" -> "), location),
+            IncludeError::CapacityConflict { resource, capacities, locations } => {
+                let capacities: Vec<String> = capacities.iter().map(|capacity| capacity.to_string()).collect();
+                write!(f, "capacity of {} is declared as {}", resource, capacities.join(" and as "))?;
+                write_locations(f, locations)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IncludeError {}
+
+// A duration or lag in the job file that is not a whole number of milliseconds, or too long to count in them, or
+// times with and without a unit in one file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeError {
+    BelowMillisecond,
+    Overflow,
+    MixedUnits,
+}
+
+impl fmt::Display for TimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeError::BelowMillisecond => write!(f, "time is more precise than a millisecond"),
+            TimeError::Overflow => write!(f, "time is too long"),
+            TimeError::MixedUnits => write!(f, "times with and without a unit in one file"),
+        }
+    }
+}
+
+impl std::error::Error for TimeError {}
+
+// Schedules that cannot be merged: a task-name in both, or times too long to count in the finer time scale of both.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeError {
+    DuplicateTaskName(String),
+    Time(TimeError),
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeError::DuplicateTaskName(name) => write!(f, "task name {} is in both schedules", name),
+            MergeError::Time(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+// A metadata key given more than once in the header of a job file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateMetadataError {
+    pub key: String,
+}
+
+impl fmt::Display for DuplicateMetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "metadata {} is given more than once", self.key)
+    }
+}
+
+impl std::error::Error for DuplicateMetadataError {}
+
+// A state file of an earlier execution that cannot be read or written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    Read {
+        file: String,
+        message: String,
+    },
+    Write {
+        file: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::Read { file, message } => write!(f, "could not read state file {}: {}", file, message),
+            StateError::Write { file, message } => write!(f, "could not write state file {}: {}", file, message),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+// every known location in the same form: (line 1, column 1; build.tasks.in line 3, column 1)
+fn write_locations(f: &mut fmt::Formatter<'_>, locations: &[Option<SourceLocation>]) -> fmt::Result {
+    let locations: Vec<String> = locations
+        .iter()
+        .flatten()
+        .map(|location| location.to_string())
+        .collect();
+
+    if locations.is_empty() { Ok(()) } else { write!(f, " ({})", locations.join("; ")) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_duplicate_task_name() {
+        let error = ScheduleError::DuplicateTaskName {
+            name: "A".to_string(),
+            first: Some(SourceLocation::new(1, 1)),
+            duplicate: Some(SourceLocation::new(3, 1)),
+        };
+        assert_eq!(error.to_string(), "task name A is not unique (line 1, column 1; line 3, column 1)");
+    }
+
+    #[test]
+    fn display_unknown_dependency() {
+        let error = ScheduleError::UnknownDependency {
+            task: "B".to_string(),
+            dependency: "C".to_string(),
+            location: Some(SourceLocation::new(2, 12)),
+        };
+        assert_eq!(error.to_string(), "task B depends on unknown task C (line 2, column 12)");
+    }
+
+    #[test]
+    fn display_locations_in_included_files() {
+        let in_file = |line: usize| SourceLocation { file: Some("build.tasks.in".to_string()), ..SourceLocation::new(line, 1) };
+        let error = ScheduleError::DuplicateTaskName {
+            name: "A".to_string(),
+            first: Some(SourceLocation::new(1, 1)),
+            duplicate: Some(in_file(3)),
+        };
+        assert_eq!(error.to_string(), "task name A is not unique (line 1, column 1; build.tasks.in line 3, column 1)");
+        let error = ScheduleError::UnknownDependency {
+            task: "build::B".to_string(),
+            dependency: "C".to_string(),
+            location: Some(in_file(2)),
+        };
+        assert_eq!(error.to_string(), "task build::B depends on unknown task C (build.tasks.in line 2, column 1)");
+    }
+
+    #[test]
+    fn display_include_errors() {
+        let error = IncludeError::Read {
+            file: "test/missing.tasks.in".to_string(),
+            message: "No such file or directory".to_string(),
+            location: Some(SourceLocation::new(2, 1)),
+        };
+        assert_eq!(error.to_string(), "could not read test/missing.tasks.in: No such file or directory (line 2, column 1)");
+        let error = IncludeError::Cycle {
+            files: vec!["a.tasks.in".to_string(), "b.tasks.in".to_string(), "a.tasks.in".to_string()],
+            location: SourceLocation { file: Some("b.tasks.in".to_string()), ..SourceLocation::new(1, 1) },
+        };
+        assert_eq!(error.to_string(), "include cycle a.tasks.in -> b.tasks.in -> a.tasks.in (b.tasks.in line 1, column 1)");
+    }
+
+    #[test]
+    fn display_capacity_exceeded() {
+        let error = ScheduleError::CapacityExceeded {
+            task: "A".to_string(),
+            resource: "gpu".to_string(),
+            required: 3,
+            capacity: 2,
+            location: Some(SourceLocation::new(2, 1)),
+        };
+        assert_eq!(error.to_string(), "task A uses 3 gpu but the capacity is 2 (line 2, column 1)");
+    }
+
+    #[test]
+    fn display_without_locations() {
+        let error = ScheduleError::Cycle {
+            tasks: vec!["A".to_string(), "B".to_string()],
+            locations: vec![None, None],
+        };
+        assert_eq!(error.to_string(), "dependency cycle A -> B -> A");
+    }
+}
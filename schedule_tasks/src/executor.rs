@@ -0,0 +1,460 @@
+use std::collections::BTreeSet;
+use std::iter::once;
+use std::process::{
+    Command,
+    Output,
+};
+use std::sync::{
+    mpsc,
+    Arc,
+    Mutex,
+};
+use std::thread;
+use std::time::{
+    Instant,
+    SystemTime,
+    UNIX_EPOCH,
+};
+use serde::{Deserialize, Serialize};
+use crate::error::ScheduleError;
+use crate::output::{
+    build_metadata_output,
+    print_table,
+    to_json,
+    ExecutedTaskOutput,
+    ExecutionOutput,
+    Format,
+};
+use crate::resources::ResourcePool;
+use crate::scheduler::Scheduler;
+use crate::state::{
+    definition_hash,
+    ExecutionState,
+    TaskState,
+};
+use crate::task::{
+    Duration,
+    TimeMoment,
+};
+use crate::time::TimeScale;
+
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    #[default]
+    Pending,
+    Succeeded,
+    Failed,
+    // not run because a task it depends on, directly or not, failed
+    Skipped,
+    // not run because it succeeded in an earlier execution, with the same definition as all the tasks it depends on
+    Reused,
+}
+
+impl TaskStatus {
+    pub fn name(self) -> &'static str {
+        match self {
+            TaskStatus::Pending => "pending",
+            TaskStatus::Succeeded => "succeeded",
+            TaskStatus::Failed => "failed",
+            TaskStatus::Skipped => "skipped",
+            TaskStatus::Reused => "reused",
+        }
+    }
+}
+
+// How a task ran: its status, its start-time and duration in milliseconds since the start of the execution, and
+// why it failed or was skipped.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskRun {
+    pub status: TaskStatus,
+    pub start_time: Option<TimeMoment>,
+    pub duration: Option<Duration>,
+    pub message: Option<String>,
+}
+
+// a command run by a worker: the task index, when it started, how long it ran and its output
+type FinishedCommand = (usize, Instant, std::time::Duration, std::io::Result<Output>);
+
+// Runs the commands of the tasks of the scheduler on a pool of workers, every task after all its dependencies
+// succeeded and within the capacities of its resources.
+#[derive(Debug, Clone)]
+pub struct Executor {
+    pub scheduler: Scheduler,
+    pub workers: usize,
+    // by task index
+    pub runs: Vec<TaskRun>,
+    // the actual duration of the execution in milliseconds
+    pub makespan: TimeMoment,
+    // how the tasks ended in this and earlier executions, saved to the state file after every task when there is one
+    pub state: ExecutionState,
+    pub state_file: Option<String>,
+}
+
+impl Executor {
+    // Unlike a scheduler an executor is kept after running, to tell whether every task succeeded.
+    pub fn run(&mut self) -> Result<String, ScheduleError> {
+        self.run_with_format(Format::Text)
+    }
+
+    pub fn run_with_format(&mut self, format: Format) -> Result<String, ScheduleError> {
+        self.execute()?;
+        Ok(match format {
+            Format::Text => self.print_output(),
+            Format::Json => self.print_json_output(),
+        })
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.runs.iter().all(|run| matches!(run.status, TaskStatus::Succeeded | TaskStatus::Reused))
+    }
+
+    // The planned start-time and duration of every task by task index, in milliseconds: a time unit counts as a second.
+    pub fn planned_times(&self) -> Vec<(TimeMoment, Duration)> {
+        let milliseconds = if self.scheduler.time_scale == TimeScale::Units { 1000 } else { 1 };
+        let mut planned_times = vec![(0, 0); self.scheduler.unscheduled_tasks.len()];
+        for (task, id) in self.scheduler.scheduled_tasks.iter().zip(&self.scheduler.graph.topological_order) {
+            planned_times[id.index()] = (task.start_time * milliseconds, task.duration * milliseconds);
+        }
+        planned_times
+    }
+
+    // The ready tasks start in the order of their planned start-time as soon as a worker is free and their resources
+    // fit. A task without a command has nothing to run and succeeds right away. When a task fails the tasks depending
+    // on it are skipped, the other tasks still run. The output of every command is printed to stderr when it ends,
+    // each line prefixed by the task-name. The tasks that succeeded in an earlier execution recorded in the state are
+    // reused, unless their definition or that of a task they depend on changed.
+    pub fn execute(&mut self) -> Result<(), ScheduleError> {
+        self.scheduler.schedule()?;
+        let planned_times = self.planned_times();
+        let tasks = &self.scheduler.unscheduled_tasks;
+        let graph = &self.scheduler.graph;
+        let workers = self.workers.max(1);
+        self.runs = vec![TaskRun::default(); tasks.len()];
+        self.state.tasks.retain(|state| graph.index_of(&state.name).is_some());
+        for index in graph.topological_order.iter().map(|id| id.index()) {
+            if self.state.succeeded_before(&tasks[index])
+                && graph.dependencies[index].iter().all(|dependency| self.runs[dependency.index()].status == TaskStatus::Reused) {
+                self.runs[index].status = TaskStatus::Reused;
+            }
+        }
+
+        let (command_sender, command_receiver) = mpsc::channel::<(usize, String)>();
+        let command_receiver = Arc::new(Mutex::new(command_receiver));
+        let (result_sender, result_receiver) = mpsc::channel::<FinishedCommand>();
+        let pool: Vec<thread::JoinHandle<()>> = (0..workers)
+            .map(|_| {
+                let commands = Arc::clone(&command_receiver);
+                let results = result_sender.clone();
+                thread::spawn(move || loop {
+                    // the lock is released before the command runs, so another worker can take the next command
+                    let next = commands.lock().map_err(|_| ()).and_then(|commands| commands.recv().map_err(|_| ()));
+                    let (index, command) = match next {
+                        Ok(next) => next,
+                        Err(()) => break,
+                    };
+                    let started = Instant::now();
+                    let output = Command::new("sh").arg("-c").arg(&command).output();
+                    if results.send((index, started, started.elapsed(), output)).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+        drop(result_sender);
+
+        let start = Instant::now();
+        // the state records moments in milliseconds since the Unix epoch, so they can be compared between executions
+        let start_since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis() as u64);
+        let milliseconds_since_start = |moment: Instant| moment.duration_since(start).as_millis() as TimeMoment;
+        let mut resources = ResourcePool { capacities: self.scheduler.resources.capacities.clone(), ..Default::default() };
+        let mut unfinished_dependencies: Vec<usize> = graph.dependencies
+            .iter()
+            .map(|dependencies| dependencies.iter().filter(|dependency| self.runs[dependency.index()].status != TaskStatus::Reused).count())
+            .collect();
+        let position_in_schedule = graph.topological_positions();
+        let ready_key = |index: usize| (planned_times[index].0, position_in_schedule[index], index);
+        let mut ready: BTreeSet<(TimeMoment, usize, usize)> = (0..tasks.len())
+            .filter(|&index| unfinished_dependencies[index] == 0 && self.runs[index].status == TaskStatus::Pending)
+            .map(ready_key)
+            .collect();
+        let mut running = 0;
+
+        loop {
+            let startable = ready
+                .iter()
+                .copied()
+                .find(|&(_, _, index)| (tasks[index].command.is_none() || running < workers) && resources.fits(&tasks[index]));
+            let (index, result) = match startable {
+                Some(key) => {
+                    ready.remove(&key);
+                    let (_, _, index) = key;
+                    let task = &tasks[index];
+                    match &task.command {
+                        Some(command) => {
+                            resources.acquire(task);
+                            running += 1;
+                            command_sender.send((index, command.clone())).expect("the workers run until all commands are sent");
+                            continue;
+                        }
+                        None => {
+                            let now = milliseconds_since_start(Instant::now());
+                            self.runs[index].start_time = Some(now);
+                            self.runs[index].duration = Some(0);
+                            (index, Ok(()))
+                        }
+                    }
+                }
+                None if running == 0 => break,
+                None => {
+                    let (index, started, elapsed, output) = result_receiver.recv().expect("a worker is running a command");
+                    running -= 1;
+                    resources.release(&tasks[index]);
+                    self.runs[index].start_time = Some(milliseconds_since_start(started));
+                    self.runs[index].duration = Some(elapsed.as_millis() as Duration);
+                    let result = match output {
+                        Ok(output) => {
+                            let stdout = String::from_utf8_lossy(&output.stdout);
+                            let stderr = String::from_utf8_lossy(&output.stderr);
+                            for line in stdout.lines().chain(stderr.lines()) {
+                                eprintln!("{}: {}", tasks[index].name, line);
+                            }
+                            if output.status.success() { Ok(()) } else { Err(output.status.to_string()) }
+                        }
+                        Err(error) => Err(format!("could not run: {}", error)),
+                    };
+                    (index, result)
+                }
+            };
+
+            let mut finished = vec![index];
+            match result {
+                Ok(()) => {
+                    self.runs[index].status = TaskStatus::Succeeded;
+                    for dependent in graph.dependents[index].iter().map(|id| id.index()) {
+                        unfinished_dependencies[dependent] -= 1;
+                        if unfinished_dependencies[dependent] == 0 && self.runs[dependent].status == TaskStatus::Pending {
+                            ready.insert(ready_key(dependent));
+                        }
+                    }
+                }
+                Err(message) => {
+                    self.runs[index].status = TaskStatus::Failed;
+                    self.runs[index].message = Some(message);
+                    let mut dependents: Vec<usize> = graph.dependents[index].iter().map(|id| id.index()).collect();
+                    while let Some(dependent) = dependents.pop() {
+                        if self.runs[dependent].status == TaskStatus::Pending {
+                            self.runs[dependent].status = TaskStatus::Skipped;
+                            self.runs[dependent].message = Some(format!("{} failed", tasks[index].name));
+                            dependents.extend(graph.dependents[dependent].iter().map(|id| id.index()));
+                            finished.push(dependent);
+                        }
+                    }
+                }
+            }
+
+            let now = milliseconds_since_start(Instant::now());
+            for index in finished {
+                let run = &self.runs[index];
+                self.state.record(TaskState {
+                    name: tasks[index].name.clone(),
+                    hash: definition_hash(&tasks[index]),
+                    status: run.status,
+                    started_at: run.start_time.map(|start_time| start_since_epoch + start_time as u64),
+                    finished_at: start_since_epoch + run.start_time.zip(run.duration).map_or(now, |(start_time, duration)| start_time + duration) as u64,
+                    message: run.message.clone(),
+                });
+            }
+            // a state file that can not be written loses the resumption, not the execution
+            if let Some(state_file) = &self.state_file {
+                if let Err(error) = self.state.save(state_file) {
+                    eprintln!("Warning: {}", error);
+                }
+            }
+        }
+
+        drop(command_sender);
+        for worker in pool {
+            worker.join().expect("a worker does not panic");
+        }
+        self.makespan = milliseconds_since_start(Instant::now());
+        Ok(())
+    }
+
+    fn count(&self, status: TaskStatus) -> usize {
+        self.runs.iter().filter(|run| run.status == status).count()
+    }
+
+    pub fn print_output(&self) -> String {
+        let time_scale = TimeScale::Milliseconds;
+        let format = |time: Option<TimeMoment>| time.map_or("-".to_string(), |time| time_scale.format(time));
+        let planned_times = self.planned_times();
+        let planned_makespan = planned_times.iter().map(|(start, duration)| start + duration).max().unwrap_or(0);
+
+        let header = ["Task", "Status", "Planned start", "Actual start", "Planned", "Actual"].map(String::from).to_vec();
+        let rows = self.scheduler.unscheduled_tasks
+            .iter()
+            .zip(&self.runs)
+            .zip(&planned_times)
+            .map(|((task, run), &(planned_start, planned_duration))| vec![
+                task.name.to_string(),
+                match &run.message {
+                    Some(message) => format!("{} ({})", run.status.name(), message),
+                    None => run.status.name().to_string(),
+                },
+                time_scale.format(planned_start),
+                format(run.start_time),
+                time_scale.format(planned_duration),
+                format(run.duration),
+            ]);
+
+        let metadata = self.scheduler.metadata.print();
+        let mut lines: Vec<String> = if metadata.is_empty() { vec![] } else { vec![metadata] };
+        lines.push(format!("Workers: {}", self.workers));
+        let mut counts = format!("Succeeded: {}, failed: {}, skipped: {}",
+                                 self.count(TaskStatus::Succeeded), self.count(TaskStatus::Failed), self.count(TaskStatus::Skipped));
+        if self.count(TaskStatus::Reused) > 0 {
+            counts.push_str(&format!(", reused: {}", self.count(TaskStatus::Reused)));
+        }
+        lines.push(counts);
+        lines.push(format!("Planned: {}, actual: {}", time_scale.format(planned_makespan), time_scale.format(self.makespan)));
+        format!("{}\n\n{}", lines.join("\n"), print_table(once(header).chain(rows).collect()))
+    }
+
+    pub fn print_json_output(&self) -> String {
+        let planned_times = self.planned_times();
+        let planned_makespan = planned_times.iter().map(|(start, duration)| start + duration).max().unwrap_or(0);
+        to_json(&ExecutionOutput {
+            metadata: build_metadata_output(&self.scheduler.metadata),
+            workers: self.workers,
+            tasks: self.scheduler.unscheduled_tasks
+                .iter()
+                .zip(&self.runs)
+                .zip(&planned_times)
+                .map(|((task, run), &(planned_start, planned_duration))| ExecutedTaskOutput {
+                    name: &task.name,
+                    command: task.command.as_deref(),
+                    status: run.status.name(),
+                    message: run.message.as_deref(),
+                    planned_start,
+                    planned_duration,
+                    actual_start: run.start_time,
+                    actual_duration: run.duration,
+                })
+                .collect(),
+            planned_makespan,
+            actual_makespan: self.makespan,
+        })
+    }
+}
+
+pub fn build_executor(scheduler: Scheduler, workers: usize) -> Executor {
+    Executor {
+        scheduler,
+        workers,
+        runs: vec![],
+        makespan: 0,
+        state: Default::default(),
+        state_file: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::parsers::parse_job;
+    use crate::scheduler::build_job_scheduler;
+
+    fn execute(input: &str, workers: usize) -> Executor {
+        let mut executor = build_executor(build_job_scheduler(parse_job(input).unwrap()), workers);
+        executor.execute().unwrap();
+        executor
+    }
+
+    fn statuses(executor: &Executor) -> Vec<TaskStatus> {
+        executor.runs.iter().map(|run| run.status).collect()
+    }
+
+    #[test]
+    fn execute_in_dependency_order() {
+        let executor = execute(r#"A(1) run "sleep 0.1"
+B(1) run "true" after [A]
+C(0) after [B]"#, 4);
+
+        assert!(executor.succeeded());
+        let a = &executor.runs[0];
+        let b = &executor.runs[1];
+        assert!(a.duration.unwrap() >= 100);
+        assert!(b.start_time.unwrap() >= a.start_time.unwrap() + a.duration.unwrap());
+        assert_eq!(executor.runs[2].duration, Some(0));
+        assert_eq!(executor.planned_times(), vec![(0, 1000), (1000, 1000), (2000, 0)]);
+    }
+
+    #[test]
+    fn skip_the_dependents_of_a_failed_task() {
+        let executor = execute(r#"A(1) run "exit 3"
+B(1) run "true" after [A]
+C(1) run "true" after [B]
+D(1) run "true"
+"#, 2);
+
+        assert!(!executor.succeeded());
+        assert_eq!(statuses(&executor), vec![TaskStatus::Failed, TaskStatus::Skipped, TaskStatus::Skipped, TaskStatus::Succeeded]);
+        assert_eq!(executor.runs[0].message.as_deref(), Some("exit status: 3"));
+        assert_eq!(executor.runs[2].message.as_deref(), Some("A failed"));
+        assert_eq!(executor.runs[2].start_time, None);
+    }
+
+    #[test]
+    fn run_no_more_commands_than_workers() {
+        let executor = execute(r#"A(1) run "sleep 0.1"
+B(1) run "sleep 0.1"
+C(1) run "sleep 0.1""#, 2);
+
+        assert!(executor.succeeded());
+        let mut start_times: Vec<TimeMoment> = executor.runs.iter().map(|run| run.start_time.unwrap()).collect();
+        start_times.sort_unstable();
+        // the third command waits for one of the first two to end
+        assert!(start_times[2] >= start_times[0] + 100);
+        assert!(executor.makespan >= 200);
+    }
+
+    #[test]
+    fn resume_from_the_state_of_an_earlier_execution() {
+        let execute_with_state = |input: &str, state: ExecutionState| {
+            let mut executor = build_executor(build_job_scheduler(parse_job(input).unwrap()), 2);
+            executor.state = state;
+            executor.execute().unwrap();
+            executor
+        };
+        let executor = execute_with_state(r#"A(1) run "true"
+B(1) run "exit 1" after [A]
+C(1) run "true"
+D(1) run "true" after [C]
+E(1) run "true" after [B, D]
+F(1) run "true"
+"#, ExecutionState::default());
+        assert_eq!(statuses(&executor), vec![
+            TaskStatus::Succeeded, TaskStatus::Failed, TaskStatus::Succeeded,
+            TaskStatus::Succeeded, TaskStatus::Skipped, TaskStatus::Succeeded,
+        ]);
+        assert_eq!(executor.state.task("E").unwrap().started_at, None);
+
+        // B is fixed, C changed so D depending on it runs again, and F is no longer in the job
+        let executor = execute_with_state(r#"A(1) run "true"
+B(1) run "true" after [A]
+C(1) run "echo C"
+D(1) run "true" after [C]
+E(1) run "true" after [B, D]
+"#, executor.state);
+        assert!(executor.succeeded());
+        assert_eq!(statuses(&executor), vec![
+            TaskStatus::Reused, TaskStatus::Succeeded, TaskStatus::Succeeded, TaskStatus::Succeeded, TaskStatus::Succeeded,
+        ]);
+        assert_eq!(executor.runs[0].start_time, None);
+        assert!(executor.state.task("F").is_none());
+        assert!(executor.state.tasks.iter().all(|task| task.status == TaskStatus::Succeeded));
+        assert!(executor.print_output().contains("Succeeded: 4, failed: 0, skipped: 0, reused: 1"));
+    }
+}
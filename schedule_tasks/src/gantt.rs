@@ -0,0 +1,184 @@
+use std::iter::once;
+use crate::scheduler::Scheduler;
+use crate::task::TimeMoment;
+
+
+// longer jobs are scaled down, so one column of the ASCII chart can cover more than one time unit
+const MAX_CHART_COLUMNS: usize = 60;
+
+const SVG_CHART_WIDTH: f64 = 600.0;
+const SVG_ROW_HEIGHT: f64 = 20.0;
+const SVG_BAR_HEIGHT: f64 = 14.0;
+const SVG_HISTOGRAM_HEIGHT: f64 = 60.0;
+const SVG_CHARACTER_WIDTH: f64 = 8.0;
+const SVG_MARGIN: f64 = 10.0;
+
+fn is_critical(scheduler: &Scheduler, position: usize) -> bool {
+    scheduler.timings.get(position).is_some_and(|timing| timing.total_float == 0)
+}
+
+// One row per task in the order of the schedule: # for tasks on a critical path, = for the others.
+// Below the time axis a histogram of the number of tasks running in parallel.
+pub fn print_ascii_gantt(scheduler: &Scheduler) -> String {
+    let makespan = scheduler.last_task.end_time;
+    let columns = makespan.min(MAX_CHART_COLUMNS);
+    let column_of = |time: TimeMoment| (time * columns).checked_div(makespan).unwrap_or(0);
+    let column_after = |time: TimeMoment| if makespan == 0 { 0 } else { (time * columns).div_ceil(makespan) };
+
+    let label_width = scheduler.scheduled_tasks
+        .iter()
+        .map(|task| task.name.chars().count())
+        .chain(once(scheduler.max_parallelism.to_string().len()))
+        .max()
+        .unwrap_or(0);
+
+    let mut lines: Vec<String> = vec![];
+    for (position, task) in scheduler.scheduled_tasks.iter().enumerate() {
+        let bar_character = if is_critical(scheduler, position) { '#' } else { '=' };
+        let from = column_of(task.start_time);
+        let to = if task.duration > 0 { column_after(task.end_time).max(from + 1) } else { from };
+        let bar: String = (0..columns).map(|column| if from <= column && column < to { bar_character } else { ' ' }).collect();
+        lines.push(format!("{:<width$} |{}|", task.name, bar, width = label_width));
+    }
+
+    lines.push(format!("{:width$} +{}+", "", "-".repeat(columns), width = label_width));
+    lines.push(format!("{:width$} 0{:>end$}", "", scheduler.time_scale.format(makespan), width = label_width, end = columns + 1));
+
+    // the highest parallelism within the time units covered by each column
+    let parallelism_per_column: Vec<usize> = (0..columns)
+        .map(|column| {
+            let (from, to) = (column * makespan / columns, (column + 1) * makespan / columns);
+            scheduler.parallelism_profile
+                .iter()
+                .filter(|interval| interval.from < to.max(from + 1) && from < interval.to)
+                .map(|interval| interval.tasks)
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for level in (1..=scheduler.max_parallelism).rev() {
+        let bar: String = parallelism_per_column.iter().map(|&tasks| if tasks >= level { '*' } else { ' ' }).collect();
+        lines.push(format!("{:>width$} |{}|", level, bar, width = label_width));
+    }
+
+    lines.push(String::new());
+    lines.push("# critical  = not critical  * tasks running in parallel".to_string());
+
+    lines.join("\n")
+}
+
+// Text for SVG, as a task-name made in code can hold any character.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;")
+}
+
+// The same chart as a standalone SVG image, with the start- and end-time of each task as tooltip.
+pub fn print_svg_gantt(scheduler: &Scheduler) -> String {
+    let makespan = scheduler.last_task.end_time;
+    let scale = if makespan == 0 { 0.0 } else { SVG_CHART_WIDTH / makespan as f64 };
+    let longest_name = scheduler.scheduled_tasks.iter().map(|task| task.name.chars().count()).max().unwrap_or(0);
+    let chart_left = SVG_MARGIN + longest_name as f64 * SVG_CHARACTER_WIDTH + SVG_MARGIN;
+    let axis_top = SVG_MARGIN + scheduler.scheduled_tasks.len() as f64 * SVG_ROW_HEIGHT;
+    let histogram_top = axis_top + 2.0 * SVG_ROW_HEIGHT;
+    let width = chart_left + SVG_CHART_WIDTH + SVG_MARGIN;
+    let height = histogram_top + SVG_HISTOGRAM_HEIGHT + SVG_MARGIN;
+    let x = |time: TimeMoment| chart_left + time as f64 * scale;
+    let time_scale = scheduler.time_scale;
+
+    let mut svg: Vec<String> = vec![
+        format!(r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="0 0 {:.0} {:.0}">"#, width, height, width, height),
+        "<style>text { font: 12px monospace; } .task { fill: #4c78a8; } .critical { fill: #d62728; } .parallelism { fill: #9e9e9e; } .axis { stroke: #000000; }</style>".to_string(),
+    ];
+
+    for (position, task) in scheduler.scheduled_tasks.iter().enumerate() {
+        let top = SVG_MARGIN + position as f64 * SVG_ROW_HEIGHT;
+        let class = if is_critical(scheduler, position) { "critical" } else { "task" };
+        let name = escape_xml(&task.name);
+        svg.push(format!(r#"<text x="{:.1}" y="{:.1}">{}</text>"#, SVG_MARGIN, top + SVG_BAR_HEIGHT - 2.0, name));
+        svg.push(format!(r#"<rect class="{}" x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}"><title>{} [{}-{}]</title></rect>"#,
+            class, x(task.start_time), top, task.duration as f64 * scale, SVG_BAR_HEIGHT, name, time_scale.format(task.start_time), time_scale.format(task.end_time)));
+    }
+
+    svg.push(format!(r#"<line class="axis" x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}"/>"#, x(0), axis_top, x(makespan), axis_top));
+    svg.push(format!(r#"<text x="{:.1}" y="{:.1}">0</text>"#, x(0), axis_top + SVG_BAR_HEIGHT));
+    svg.push(format!(r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{}</text>"#, x(makespan), axis_top + SVG_BAR_HEIGHT, time_scale.format(makespan)));
+
+    if scheduler.max_parallelism > 0 {
+        let level_height = SVG_HISTOGRAM_HEIGHT / scheduler.max_parallelism as f64;
+        for interval in scheduler.parallelism_profile.iter().filter(|interval| interval.tasks > 0) {
+            let bar_height = interval.tasks as f64 * level_height;
+            svg.push(format!(r#"<rect class="parallelism" x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}"><title>{} tasks [{}-{}]</title></rect>"#,
+                x(interval.from), histogram_top + SVG_HISTOGRAM_HEIGHT - bar_height, (interval.to - interval.from) as f64 * scale,
+                bar_height, interval.tasks, time_scale.format(interval.from), time_scale.format(interval.to)));
+        }
+    }
+
+    svg.push("</svg>".to_string());
+    svg.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::build_scheduler;
+    use crate::task::Task;
+
+    #[test]
+    fn print_ascii_gantt_chart() {
+        let a = Task::new("A", 1, vec![]);
+        let b = Task::new("B", 1, vec!["A".to_string()]);
+        let c = Task::new("C", 3, vec!["A".to_string()]);
+
+        let mut scheduler = build_scheduler(vec![a, b, c]);
+        scheduler.schedule().unwrap();
+
+        assert_eq!(print_ascii_gantt(&scheduler), r#"A |#   |
+B | =  |
+C | ###|
+  +----+
+  0    4
+2 | *  |
+1 |****|
+
+# critical  = not critical  * tasks running in parallel"#);
+    }
+
+    #[test]
+    fn print_ascii_gantt_chart_scaled_down() {
+        let a = Task::new("A", 100, vec![]);
+        let b = Task::new("B", 20, vec!["A".to_string()]);
+
+        let mut scheduler = build_scheduler(vec![a, b]);
+        scheduler.schedule().unwrap();
+
+        let chart = print_ascii_gantt(&scheduler);
+        assert!(chart.starts_with(&format!("A |{}{}|\nB |{}{}|", "#".repeat(50), " ".repeat(10), " ".repeat(50), "#".repeat(10))));
+    }
+
+    #[test]
+    fn print_svg_gantt_chart() {
+        let a = Task::new("A", 1, vec![]);
+        let b = Task::new("B", 1, vec!["A".to_string()]);
+        let c = Task::new("C", 3, vec!["A".to_string()]);
+
+        let mut scheduler = build_scheduler(vec![a, b, c]);
+        scheduler.schedule().unwrap();
+
+        let svg = print_svg_gantt(&scheduler);
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg""#));
+        assert!(svg.ends_with("</svg>"));
+        assert!(svg.contains(r#"<rect class="critical" x="28.0" y="10.0" width="150.0" height="14.0"><title>A [0-1]</title></rect>"#));
+        assert!(svg.contains(r#"<rect class="task" x="178.0" y="30.0" width="150.0" height="14.0"><title>B [1-2]</title></rect>"#));
+        assert!(svg.contains("<title>2 tasks [1-2]</title>"));
+    }
+
+    #[test]
+    fn print_svg_gantt_chart_with_markup_in_names() {
+        let mut scheduler = build_scheduler(vec![Task::new("<b> & \"c\"", 1, vec![])]);
+        scheduler.schedule().unwrap();
+
+        let svg = print_svg_gantt(&scheduler);
+        assert!(svg.contains(">&lt;b&gt; &amp; &quot;c&quot;</text>"));
+        assert!(svg.contains("<title>&lt;b&gt; &amp; &quot;c&quot; [0-1]</title>"));
+    }
+}
@@ -0,0 +1,382 @@
+use std::cmp::Reverse;
+use std::collections::{
+    BinaryHeap,
+    HashMap,
+};
+use serde::{Deserialize, Serialize};
+use crate::error::ScheduleError;
+use crate::task::{
+    Dependency,
+    SourceLocation,
+    Task,
+    TaskId,
+};
+
+
+// The task-names of a job by TaskId, the index of the task, and the TaskId of each name. A name used by more than
+// one task refers to the first of them.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameTable {
+    names: Vec<String>,
+    ids: HashMap<String, TaskId>,
+}
+
+impl NameTable {
+    // adds the name of the next task, returning the id of an earlier task with the same name
+    pub fn insert(&mut self, name: &str) -> Option<TaskId> {
+        let id = TaskId(self.names.len());
+        self.names.push(name.to_string());
+        match self.ids.get(name) {
+            Some(&first) => Some(first),
+            None => {
+                self.ids.insert(name.to_string(), id);
+                None
+            }
+        }
+    }
+
+    pub fn id(&self, name: &str) -> Option<TaskId> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: TaskId) -> &str {
+        &self.names[id.index()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+pub fn build_name_table(tasks: &[Task]) -> NameTable {
+    let mut names = NameTable::default();
+    for task in tasks {
+        names.insert(&task.name);
+    }
+    names
+}
+
+// The dependencies between the tasks of a job by TaskId, so following them takes an index instead of comparing names.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Graph {
+    pub names: NameTable,
+    pub dependencies: Vec<Vec<TaskId>>,
+    pub dependents: Vec<Vec<TaskId>>,
+    pub topological_order: Vec<TaskId>,
+}
+
+impl Graph {
+    pub fn number_of_tasks(&self) -> usize {
+        self.dependencies.len()
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<TaskId> {
+        self.names.id(name)
+    }
+
+    // the inverse of the topological order: the position of each task in that order, by task index
+    pub fn topological_positions(&self) -> Vec<usize> {
+        let mut positions = vec![0; self.number_of_tasks()];
+        for (position, &id) in self.topological_order.iter().enumerate() {
+            positions[id.index()] = position;
+        }
+        positions
+    }
+
+    pub fn number_of_dependencies(&self) -> Vec<usize> {
+        self.dependencies
+            .iter()
+            .map(|dependencies| dependencies.len())
+            .collect()
+    }
+
+    fn ordered_tasks(&self) -> Vec<bool> {
+        let mut ordered = vec![false; self.number_of_tasks()];
+        for &id in &self.topological_order {
+            ordered[id.index()] = true;
+        }
+        ordered
+    }
+
+    // Every task that is not ordered waits for at least one other such task. Walking from one of them
+    // along those dependencies must therefore return to a task already seen: the cycle. It is returned
+    // in the order in which the tasks would run, starting with the task listed first.
+    fn find_cycle(&self, ordered: &[bool]) -> Vec<TaskId> {
+        let mut position_in_walk: Vec<Option<usize>> = vec![None; self.number_of_tasks()];
+        let mut walk = vec![];
+        let mut current = ordered.iter().position(|&ordered| !ordered).map(TaskId);
+
+        while let Some(id) = current {
+            if let Some(position) = position_in_walk[id.index()] {
+                let mut cycle = walk.split_off(position);
+                cycle.reverse();
+                let first = (0..cycle.len()).min_by_key(|&position| cycle[position]).unwrap_or(0);
+                cycle.rotate_left(first);
+                return cycle;
+            }
+            position_in_walk[id.index()] = Some(walk.len());
+            walk.push(id);
+            current = self.dependencies[id.index()].iter().copied().find(|dependency| !ordered[dependency.index()]);
+        }
+
+        walk
+    }
+
+    // Kahn's algorithm. Of all tasks that are ready, the one listed first in the input file goes first,
+    // so the order equals the order of the original scan loop. Tasks on or behind a cycle are left out.
+    fn sort_topologically(&mut self) {
+        let mut number_of_open_dependencies = self.number_of_dependencies();
+        let mut ready: BinaryHeap<Reverse<TaskId>> = number_of_open_dependencies
+            .iter()
+            .enumerate()
+            .filter(|(_, &open)| open == 0)
+            .map(|(index, _)| Reverse(TaskId(index)))
+            .collect();
+        self.topological_order = Vec::with_capacity(self.number_of_tasks());
+
+        while let Some(Reverse(id)) = ready.pop() {
+            self.topological_order.push(id);
+            for &dependent in &self.dependents[id.index()] {
+                number_of_open_dependencies[dependent.index()] -= 1;
+                if number_of_open_dependencies[dependent.index()] == 0 {
+                    ready.push(Reverse(dependent));
+                }
+            }
+        }
+    }
+}
+
+// The graph of the tasks, or the first violation of the job consistency rules.
+pub fn build_graph(tasks: &[Task]) -> Result<Graph, ScheduleError> {
+    let (graph, errors) = build_graph_with_errors(tasks);
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(graph),
+    }
+}
+
+// From here on every dependency refers to its task by id, so scheduling follows the id instead of the name.
+pub fn resolve_dependencies(tasks: &mut [Task], names: &NameTable) {
+    for dependency in tasks.iter_mut().flat_map(|task| task.dependencies.iter_mut()) {
+        dependency.id = names.id(&dependency.name);
+    }
+}
+
+// where the dependency is written, or the task for a task made in code
+fn dependency_location(task: &Task, dependency: &Dependency) -> Option<SourceLocation> {
+    dependency.location.clone().or_else(|| task.location.clone())
+}
+
+fn cycle_error(tasks: &[Task], cycle: &[TaskId]) -> ScheduleError {
+    ScheduleError::Cycle {
+        tasks: cycle.iter().map(|id| tasks[id.index()].name.to_string()).collect(),
+        locations: cycle.iter().map(|id| tasks[id.index()].location.clone()).collect(),
+    }
+}
+
+// Every violation of the job consistency rules instead of only the first, so a job file can be fixed in one pass:
+// duplicate task-names, unknown dependencies, tasks depending on themselves and every dependency cycle.
+pub fn check_tasks(tasks: &[Task]) -> Vec<ScheduleError> {
+    build_graph_with_errors(tasks).1
+}
+
+// The graph is built once for scheduling and checking alike, with every error found on the way. Unknown dependencies
+// and dependencies of a task on itself are left out of the graph, which is only scheduled when there are no errors.
+fn build_graph_with_errors(tasks: &[Task]) -> (Graph, Vec<ScheduleError>) {
+    let mut errors = vec![];
+    let mut names = NameTable::default();
+    for task in tasks {
+        if let Some(first) = names.insert(&task.name) {
+            errors.push(ScheduleError::DuplicateTaskName {
+                name: task.name.to_string(),
+                first: tasks[first.index()].location.clone(),
+                duplicate: task.location.clone(),
+            });
+        }
+    }
+
+    // self-dependencies are reported on their own and left out of the graph, so the cycles found are the others
+    let mut dependencies = vec![vec![]; tasks.len()];
+    let mut dependents = vec![vec![]; tasks.len()];
+    for (index, task) in tasks.iter().enumerate() {
+        for dependency in &task.dependencies {
+            match names.id(&dependency.name) {
+                Some(dependency_id) if dependency_id.index() == index => errors.push(ScheduleError::SelfDependency {
+                    task: task.name.to_string(),
+                    location: dependency_location(task, dependency),
+                }),
+                Some(dependency_id) => {
+                    dependencies[index].push(dependency_id);
+                    dependents[dependency_id.index()].push(TaskId(index));
+                }
+                None => errors.push(ScheduleError::UnknownDependency {
+                    task: task.name.to_string(),
+                    dependency: dependency.name.clone(),
+                    location: dependency_location(task, dependency),
+                }),
+            }
+        }
+    }
+
+    let mut graph = Graph {
+        names,
+        dependencies,
+        dependents,
+        topological_order: vec![],
+    };
+    graph.sort_topologically();
+
+    // After a cycle is reported its tasks count as ordered, and so does every task that then only waits for ordered
+    // tasks. The tasks left over wait for another cycle.
+    let mut ordered = graph.ordered_tasks();
+    let mut number_of_open_dependencies: Vec<usize> = graph.dependencies
+        .iter()
+        .map(|dependencies| dependencies.iter().filter(|dependency| !ordered[dependency.index()]).count())
+        .collect();
+    while ordered.contains(&false) {
+        let cycle = graph.find_cycle(&ordered);
+        errors.push(cycle_error(tasks, &cycle));
+
+        let mut newly_ordered = cycle;
+        while let Some(id) = newly_ordered.pop() {
+            if ordered[id.index()] {
+                continue;
+            }
+            ordered[id.index()] = true;
+            for &dependent in &graph.dependents[id.index()] {
+                number_of_open_dependencies[dependent.index()] -= 1;
+                if number_of_open_dependencies[dependent.index()] == 0 {
+                    newly_ordered.push(dependent);
+                }
+            }
+        }
+    }
+
+    (graph, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::SourceLocation;
+
+    #[test]
+    fn name_table() {
+        let mut names = build_name_table(&[Task::new("A", 1, vec![]), Task::new("B", 1, vec![])]);
+        assert_eq!(names.id("B"), Some(TaskId(1)));
+        assert_eq!(names.id("C"), None);
+        assert_eq!(names.name(TaskId(0)), "A");
+        assert_eq!(names.len(), 2);
+        // a duplicate gets the next id, but its name still finds the first task
+        assert_eq!(names.insert("A"), Some(TaskId(0)));
+    }
+
+    #[test]
+    fn resolve_dependencies_to_ids() {
+        let mut tasks = vec![Task::new("A", 1, vec![]), Task::new("B", 1, vec!["A".to_string()])];
+        let graph = build_graph(&tasks).unwrap();
+        resolve_dependencies(&mut tasks, &graph.names);
+        assert_eq!(tasks[1].dependencies[0].id, Some(TaskId(0)));
+    }
+
+    #[test]
+    fn build_graph_adjacency() {
+        let a = Task::new("A", 1, vec![]);
+        let b = Task::new("B", 1, vec!["A".to_string()]);
+        let c = Task::new("C", 1, vec!["A".to_string(), "B".to_string()]);
+
+        let graph = build_graph(&[a, b, c]).unwrap();
+
+        assert_eq!(graph.index_of("C"), Some(TaskId(2)));
+        assert_eq!(graph.dependencies, vec![vec![], vec![TaskId(0)], vec![TaskId(0), TaskId(1)]]);
+        assert_eq!(graph.dependents, vec![vec![TaskId(1), TaskId(2)], vec![TaskId(2)], vec![]]);
+    }
+
+    #[test]
+    fn topological_order_prefers_file_order() {
+        let c = Task::new("C", 1, vec!["A".to_string()]);
+        let b = Task::new("B", 1, vec![]);
+        let a = Task::new("A", 1, vec![]);
+
+        let graph = build_graph(&[c, b, a]).unwrap();
+
+        assert_eq!(graph.topological_order, vec![TaskId(1), TaskId(2), TaskId(0)]);
+    }
+
+    #[test]
+    fn build_graph_with_cycle() {
+        let a = Task::new("A", 1, vec![]).at(1, 1);
+        let b = Task::new("B", 1, vec!["A".to_string(), "C".to_string()]).at(2, 1);
+        let c = Task::new("C", 1, vec!["B".to_string()]).at(3, 1);
+
+        assert_eq!(build_graph(&[a, b, c]), Err(ScheduleError::Cycle {
+            tasks: vec!["B".to_string(), "C".to_string()],
+            locations: vec![Some(SourceLocation::new(2, 1)), Some(SourceLocation::new(3, 1))],
+        }));
+    }
+
+    #[test]
+    fn build_graph_reports_only_the_tasks_on_the_cycle() {
+        let a = Task::new("A", 1, vec!["C".to_string()]).at(1, 1);
+        let b = Task::new("B", 1, vec!["A".to_string()]).at(2, 1);
+        let c = Task::new("C", 1, vec!["B".to_string()]).at(3, 1);
+        let d = Task::new("D", 1, vec!["A".to_string()]).at(4, 1);
+        let e = Task::new("E", 1, vec!["E".to_string()]).at(5, 1);
+
+        let graph_with_cycle = build_graph(&[d, a, b, c]);
+        assert_eq!(graph_with_cycle.unwrap_err().to_string(), "dependency cycle A -> B -> C -> A (line 1, column 1; line 2, column 1; line 3, column 1)");
+
+        let graph_with_self_dependency = build_graph(&[e]);
+        assert_eq!(graph_with_self_dependency.unwrap_err().to_string(), "task E depends on itself (line 5, column 1)");
+    }
+
+    #[test]
+    fn check_tasks_reports_every_error() {
+        let a = Task::new("A", 1, vec!["B".to_string()]).at(1, 1);
+        let b = Task::new("B", 1, vec!["A".to_string()]).at(2, 1);
+        let c = Task::new("C", 1, vec!["A".to_string(), "X".to_string()]).at(3, 1);
+        let d = Task::new("D", 1, vec!["E".to_string()]).at(4, 1);
+        let e = Task::new("E", 1, vec!["D".to_string(), "E".to_string()]).at(5, 1);
+        let duplicate = Task::new("A", 1, vec![]).at(6, 1);
+        let f = Task::new("F", 1, vec![]).at(7, 1);
+
+        let errors: Vec<String> = check_tasks(&[a, b, c, d, e, duplicate, f]).iter().map(|error| error.to_string()).collect();
+
+        assert_eq!(errors, vec![
+            "task name A is not unique (line 1, column 1; line 6, column 1)",
+            "task C depends on unknown task X (line 3, column 1)",
+            "task E depends on itself (line 5, column 1)",
+            "dependency cycle A -> B -> A (line 1, column 1; line 2, column 1)",
+            "dependency cycle D -> E -> D (line 4, column 1; line 5, column 1)",
+        ]);
+        assert!(check_tasks(&[Task::new("A", 1, vec![]), Task::new("B", 1, vec!["A".to_string()])]).is_empty());
+    }
+
+    #[test]
+    fn build_graph_with_non_existing_dependency() {
+        let a = Task::new("A", 1, vec![]).at(1, 1);
+        let b = Task::new("B", 1, vec!["C".to_string()]).at(2, 1);
+
+        assert_eq!(build_graph(&[a, b]), Err(ScheduleError::UnknownDependency {
+            task: "B".to_string(),
+            dependency: "C".to_string(),
+            location: Some(SourceLocation::new(2, 1)),
+        }));
+    }
+
+    #[test]
+    fn build_graph_with_duplicate_taskname() {
+        let a = Task::new("A", 1, vec![]).at(1, 1);
+        let b = Task::new("A", 1, vec![]).at(2, 1);
+
+        assert_eq!(build_graph(&[a, b]), Err(ScheduleError::DuplicateTaskName {
+            name: "A".to_string(),
+            first: Some(SourceLocation::new(1, 1)),
+            duplicate: Some(SourceLocation::new(2, 1)),
+        }));
+    }
+}
@@ -0,0 +1,615 @@
+use std::cmp::Reverse;
+use std::collections::{
+    BTreeMap,
+    BTreeSet,
+    HashSet,
+};
+use std::iter::once;
+use serde::{Deserialize, Serialize};
+use crate::diff::{
+    diff_jobs,
+    diff_schedules,
+    MovedTask,
+    ScheduleDiff,
+    TaskDiff,
+};
+use crate::error::ScheduleError;
+use crate::graph::{
+    build_graph,
+    resolve_dependencies,
+};
+use crate::policy::Policy;
+use crate::resources::build_resource_pool;
+use crate::scheduler::{
+    build_job_scheduler,
+    build_scheduler,
+    Scheduler,
+};
+use crate::task::{
+    Dependency,
+    DependencyKind,
+    Duration,
+    Job,
+    ParallelismInterval,
+    Schedule,
+    Task,
+    TaskTiming,
+    TimeMoment,
+};
+
+
+// The difference with the previous version of the job and how many of its tasks had to be scheduled again.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleUpdate {
+    pub diff: ScheduleDiff,
+    pub rescheduled: usize,
+    pub tasks: usize,
+}
+
+impl ScheduleUpdate {
+    pub fn print(&self) -> String {
+        format!("Rescheduled {} of {} tasks\n{}", self.rescheduled, self.tasks, self.diff.print())
+    }
+}
+
+// Keeps the last version of a job and its schedule, with the times and floats of its tasks, so a new version is
+// scheduled from the tasks it changes: a task is placed again only when it changed or a task it depends on moved, and
+// its latest times are derived again only when it changed or a task depending on it did. A job with resources is list
+// scheduled, where one change can move every task starting after it, so it is scheduled in full. The scheduler is to
+// be read between updates, not changed.
+#[derive(Debug, Clone)]
+pub struct IncrementalScheduler {
+    pub policy: Policy,
+    pub job: Job,
+    pub scheduler: Scheduler,
+    // None until a version without resources has been scheduled
+    state: Option<IncrementalState>,
+}
+
+// What an update needs besides the schedule, by task index: the position in the schedule, the start- and end-time,
+// the dependents with the position of the dependency in their list, how long before the minimum duration the task has
+// to finish at the latest (its tail), and how much later it could end without delaying a dependent. Besides, every
+// end-time with the position of its task, so the last task is the first of those ending last, and per time node how
+// many start- and end-times fall on it and how the number of running tasks changes there.
+#[derive(Debug, Default, Clone)]
+struct IncrementalState {
+    positions: Vec<usize>,
+    start_times: Vec<TimeMoment>,
+    end_times: Vec<TimeMoment>,
+    dependent_edges: Vec<Vec<(usize, usize)>>,
+    tails: Vec<isize>,
+    slacks: Vec<Option<isize>>,
+    end_times_by_position: BTreeSet<(TimeMoment, Reverse<usize>)>,
+    time_nodes: BTreeMap<TimeMoment, usize>,
+    running_changes: BTreeMap<TimeMoment, isize>,
+}
+
+// By index in the new version: the tasks to place again, those new to the schedule and those whose tail can change,
+// with the times of the removed tasks.
+#[derive(Debug, Default)]
+struct Changes {
+    changed: Vec<usize>,
+    added: HashSet<usize>,
+    tail_changes: Vec<usize>,
+    removed_times: Vec<(TimeMoment, TimeMoment)>,
+}
+
+fn build_dependent_edges(tasks: &[Task]) -> Vec<Vec<(usize, usize)>> {
+    let mut edges = vec![vec![]; tasks.len()];
+    for (index, task) in tasks.iter().enumerate() {
+        for (position, dependency) in task.dependencies.iter().enumerate() {
+            if let Some(id) = dependency.id {
+                edges[id.index()].push((index, position));
+            }
+        }
+    }
+    edges
+}
+
+// the values of the tasks in the previous version by their index in the new one, a default for an added task
+fn carry<T: Copy + Default>(previous_indices: &[Option<usize>], values: &[T]) -> Vec<T> {
+    previous_indices.iter().map(|previous| previous.map_or_else(T::default, |index| values[index])).collect()
+}
+
+fn critical_path(scheduler: &Scheduler) -> Vec<String> {
+    scheduler.critical_path_names().into_iter().rev().map(str::to_string).collect()
+}
+
+impl IncrementalState {
+    // from a schedule of the whole job, computing the tails and slacks in reverse topological order
+    fn build(scheduler: &mut Scheduler) -> Self {
+        let tasks = &scheduler.unscheduled_tasks;
+        let positions = scheduler.graph.topological_positions();
+        let mut state = IncrementalState {
+            start_times: positions.iter().map(|&position| scheduler.scheduled_tasks[position].start_time).collect(),
+            end_times: positions.iter().map(|&position| scheduler.scheduled_tasks[position].end_time).collect(),
+            positions,
+            dependent_edges: build_dependent_edges(tasks),
+            tails: vec![0; tasks.len()],
+            slacks: vec![None; tasks.len()],
+            time_nodes: BTreeMap::from([(0, 1)]),
+            ..Default::default()
+        };
+        for (position, task) in scheduler.scheduled_tasks.iter().enumerate() {
+            state.end_times_by_position.insert((task.end_time, Reverse(position)));
+            state.count_times((task.start_time, task.end_time), 1, &mut scheduler.scheduled_tasks_time_nodes);
+        }
+        for index in scheduler.graph.topological_order.iter().rev().map(|id| id.index()) {
+            state.tails[index] = state.tail(tasks, index);
+            state.slacks[index] = state.slack(tasks, index);
+        }
+        state
+    }
+
+    // The latest finish is the minimum duration less the tail. Every dependent limits it as in the backward pass of the
+    // scheduler, which does not depend on the minimum duration itself.
+    fn tail(&self, tasks: &[Task], index: usize) -> isize {
+        let duration = tasks[index].duration as isize;
+        self.dependent_edges[index]
+            .iter()
+            .map(|&(dependent, position)| {
+                let dependency = &tasks[dependent].dependencies[position];
+                let dependent_tail = self.tails[dependent] + dependency.lag;
+                match dependency.kind {
+                    DependencyKind::FinishToStart => dependent_tail + tasks[dependent].duration as isize,
+                    DependencyKind::StartToStart => dependent_tail + tasks[dependent].duration as isize - duration,
+                    DependencyKind::FinishToFinish => dependent_tail,
+                }
+            })
+            .max()
+            .unwrap_or(0)
+            .max(0)
+    }
+
+    fn slack(&self, tasks: &[Task], index: usize) -> Option<isize> {
+        self.dependent_edges[index]
+            .iter()
+            .map(|&(dependent, position)| {
+                let dependency = &tasks[dependent].dependencies[position];
+                let earliest_start = dependency.earliest_start(self.start_times[index], self.end_times[index], tasks[dependent].duration);
+                self.start_times[dependent] as isize - earliest_start
+            })
+            .min()
+    }
+
+    fn timing(&self, tasks: &[Task], index: usize, makespan: TimeMoment) -> TaskTiming {
+        let (start_time, end_time) = (self.start_times[index], self.end_times[index]);
+        let latest_finish = (makespan as isize - self.tails[index]) as TimeMoment;
+        let float_to_end = (makespan - end_time) as isize;
+        TaskTiming {
+            earliest_start: start_time,
+            earliest_finish: end_time,
+            latest_start: latest_finish - tasks[index].duration,
+            latest_finish,
+            total_float: latest_finish - end_time,
+            free_float: self.slacks[index].map_or(float_to_end, |slack| slack.min(float_to_end)) as Duration,
+        }
+    }
+
+    // counts the times of a task in, or out with a negative sign, keeping the time nodes of the scheduler in step
+    fn count_times(&mut self, (start_time, end_time): (TimeMoment, TimeMoment), sign: isize, time_nodes: &mut BTreeSet<TimeMoment>) {
+        for time in [start_time, end_time] {
+            let count = self.time_nodes.entry(time).or_insert(0);
+            *count = (*count as isize + sign) as usize;
+            if *count == 0 {
+                self.time_nodes.remove(&time);
+                time_nodes.remove(&time);
+            } else {
+                time_nodes.insert(time);
+            }
+        }
+        if end_time > start_time {
+            for (time, change) in [(start_time, sign), (end_time, -sign)] {
+                let running = self.running_changes.entry(time).or_insert(0);
+                *running += change;
+                if *running == 0 {
+                    self.running_changes.remove(&time);
+                }
+            }
+        }
+    }
+}
+
+impl IncrementalScheduler {
+    // Schedules the new version of the job. The first version is compared to an empty job, so all its tasks are added.
+    // After an error the previous version is kept.
+    pub fn update(&mut self, job: Job) -> Result<ScheduleUpdate, ScheduleError> {
+        let diff = diff_jobs(&self.job, &job);
+        let uses_resources = job.tasks.iter().any(|task| !task.resources.is_empty());
+        // times in ticks of another length can not be kept
+        if self.state.is_none() || uses_resources || job.time_scale != self.job.time_scale {
+            return self.schedule_in_full(job, diff, uses_resources);
+        }
+
+        let (makespan, critical_path_before, max_parallelism) = (self.scheduler.last_task.end_time, critical_path(&self.scheduler), self.scheduler.max_parallelism);
+        let changes = if self.has_same_graph(&job, &diff) { self.replace_changed_tasks(&job, &diff) } else { self.rebuild_graph(&job, &diff)? };
+        let (rescheduled, moved) = self.propagate(changes);
+        self.scheduler.metadata = job.metadata.clone();
+        self.scheduler.resources = build_resource_pool(&job.capacities);
+        let update = ScheduleUpdate {
+            diff: ScheduleDiff {
+                tasks: diff,
+                moved,
+                makespan: (makespan, self.scheduler.last_task.end_time),
+                critical_path: (critical_path_before, critical_path(&self.scheduler)),
+                max_parallelism: (max_parallelism, self.scheduler.max_parallelism),
+                time_scale: job.time_scale,
+            },
+            rescheduled,
+            tasks: self.scheduler.scheduled_tasks.len(),
+        };
+
+        self.job = job;
+        Ok(update)
+    }
+
+    fn schedule_in_full(&mut self, job: Job, diff: TaskDiff, uses_resources: bool) -> Result<ScheduleUpdate, ScheduleError> {
+        let mut scheduler = build_job_scheduler(job.clone());
+        scheduler.policy = self.policy;
+        scheduler.schedule()?;
+        let update = ScheduleUpdate {
+            diff: diff_schedules(&self.scheduler, &scheduler, diff),
+            rescheduled: scheduler.scheduled_tasks.len(),
+            tasks: scheduler.scheduled_tasks.len(),
+        };
+
+        self.state = (!uses_resources).then(|| IncrementalState::build(&mut scheduler));
+        self.job = job;
+        self.scheduler = scheduler;
+        Ok(update)
+    }
+
+    // the same tasks in the same order, depending on the same tasks, so the graph and the task ids still hold
+    fn has_same_graph(&self, job: &Job, diff: &TaskDiff) -> bool {
+        let same_names = |old: &[Dependency], new: &[Dependency]| old.iter().map(|dependency| &dependency.name).eq(new.iter().map(|dependency| &dependency.name));
+        diff.added.is_empty()
+            && diff.removed.is_empty()
+            && self.job.tasks.iter().map(|task| &task.name).eq(job.tasks.iter().map(|task| &task.name))
+            && diff.changed.iter().all(|task| task.dependencies.as_ref().is_none_or(|(old, new)| same_names(old, new)))
+    }
+
+    // With the same graph only the changed tasks are replaced. Their tails and those of the tasks they depend on can change.
+    fn replace_changed_tasks(&mut self, job: &Job, diff: &TaskDiff) -> Changes {
+        let scheduler = &mut self.scheduler;
+        let state = self.state.as_ref().expect("an update keeps its state");
+        let mut changes = Changes::default();
+        for changed_task in &diff.changed {
+            let id = scheduler.graph.index_of(&changed_task.name).expect("a changed task is in both versions");
+            let mut task = job.tasks[id.index()].clone();
+            resolve_dependencies(std::slice::from_mut(&mut task), &scheduler.graph.names);
+            scheduler.unscheduled_tasks[id.index()] = task.clone();
+            let scheduled_task = &mut scheduler.scheduled_tasks[state.positions[id.index()]];
+            task.start_time = scheduled_task.start_time;
+            task.end_time = scheduled_task.end_time;
+            *scheduled_task = task;
+
+            changes.changed.push(id.index());
+            changes.tail_changes.push(id.index());
+            changes.tail_changes.extend(scheduler.graph.dependencies[id.index()].iter().map(|id| id.index()));
+        }
+        changes
+    }
+
+    // Adding or removing a task or a dependency changes the task ids and the topological order, which follow the file,
+    // so the graph is built again. The times, tails and floats of the tasks in both versions are taken over by name.
+    fn rebuild_graph(&mut self, job: &Job, diff: &TaskDiff) -> Result<Changes, ScheduleError> {
+        let graph = build_graph(&job.tasks)?;
+        let mut tasks = job.tasks.clone();
+        resolve_dependencies(&mut tasks, &graph.names);
+
+        let previous = self.state.take().expect("an update keeps its state");
+        let previous_graph = &self.scheduler.graph;
+        let previous_index = |name: &str| previous_graph.index_of(name).map(|id| id.index());
+        let previous_indices: Vec<Option<usize>> = tasks.iter().map(|task| previous_index(&task.name)).collect();
+        let mut state = IncrementalState {
+            positions: graph.topological_positions(),
+            start_times: carry(&previous_indices, &previous.start_times),
+            end_times: carry(&previous_indices, &previous.end_times),
+            dependent_edges: build_dependent_edges(&tasks),
+            tails: carry(&previous_indices, &previous.tails),
+            slacks: carry(&previous_indices, &previous.slacks),
+            time_nodes: previous.time_nodes,
+            running_changes: previous.running_changes,
+            ..Default::default()
+        };
+
+        let mut changes = Changes::default();
+        let new_index = |name: &str| graph.index_of(name).map(|id| id.index());
+        for name in &diff.removed {
+            let index = previous_index(name).expect("a removed task is in the previous version");
+            let times = (previous.start_times[index], previous.end_times[index]);
+            state.count_times(times, -1, &mut self.scheduler.scheduled_tasks_time_nodes);
+            changes.removed_times.push(times);
+            changes.tail_changes.extend(previous_graph.dependencies[index].iter().filter_map(|id| new_index(previous_graph.names.name(*id))));
+        }
+        for name in diff.added.iter().chain(diff.changed.iter().map(|task| &task.name)) {
+            let index = new_index(name).expect("an added or changed task is in the new version");
+            changes.changed.push(index);
+            changes.tail_changes.push(index);
+            changes.tail_changes.extend(graph.dependencies[index].iter().map(|id| id.index()));
+            if let Some(previous) = previous_indices[index] {
+                changes.tail_changes.extend(previous_graph.dependencies[previous].iter().filter_map(|id| new_index(previous_graph.names.name(*id))));
+            }
+        }
+        changes.added = diff.added.iter().filter_map(|name| new_index(name)).collect();
+
+        let timings = graph.topological_order
+            .iter()
+            .map(|id| previous_indices[id.index()].map_or_else(TaskTiming::default, |index| self.scheduler.timings[previous.positions[index]]))
+            .collect();
+        let scheduled_tasks = graph.topological_order
+            .iter()
+            .map(|id| {
+                let mut task = tasks[id.index()].clone();
+                task.start_time = state.start_times[id.index()];
+                task.end_time = state.end_times[id.index()];
+                task
+            })
+            .collect();
+        for (index, previous) in previous_indices.iter().enumerate() {
+            if previous.is_some() {
+                state.end_times_by_position.insert((state.end_times[index], Reverse(state.positions[index])));
+            }
+        }
+
+        self.scheduler.resource_dependencies = vec![vec![]; tasks.len()];
+        self.scheduler.unscheduled_tasks = tasks;
+        self.scheduler.scheduled_tasks = scheduled_tasks;
+        self.scheduler.timings = timings;
+        self.scheduler.graph = graph;
+        self.state = Some(state);
+        Ok(changes)
+    }
+
+    // Places the changed tasks again and, in topological order, the tasks depending on a task that moved, then derives
+    // the tails and floats again where they can change, and the parallelism between the first and the last time that
+    // changed. Returns the number of tasks placed again and the tasks that moved.
+    fn propagate(&mut self, changes: Changes) -> (usize, Vec<MovedTask>) {
+        let scheduler = &mut self.scheduler;
+        let state = self.state.as_mut().expect("an update keeps its state");
+        let tasks = &scheduler.unscheduled_tasks;
+        let graph = &scheduler.graph;
+
+        let mut queue: BTreeSet<usize> = changes.changed.iter().map(|&index| state.positions[index]).collect();
+        let mut rescheduled = 0;
+        let mut moved_tasks: Vec<(usize, Option<(TimeMoment, TimeMoment)>)> = vec![];
+        while let Some(position) = queue.pop_first() {
+            let index = graph.topological_order[position].index();
+            let start_time = tasks[index].earliest_start(&state.start_times, &state.end_times);
+            let times = (start_time, start_time + tasks[index].duration);
+            let previous_times = (!changes.added.contains(&index)).then_some((state.start_times[index], state.end_times[index]));
+            rescheduled += 1;
+            if previous_times != Some(times) {
+                (state.start_times[index], state.end_times[index]) = times;
+                moved_tasks.push((index, previous_times));
+                queue.extend(graph.dependents[index].iter().map(|dependent| state.positions[dependent.index()]));
+            }
+        }
+
+        let mut window: Option<(TimeMoment, TimeMoment)> = None;
+        let mut widen = |(start_time, end_time): (TimeMoment, TimeMoment)| {
+            window = Some(window.map_or((start_time, end_time), |(from, to)| (from.min(start_time), to.max(end_time))));
+        };
+        changes.removed_times.iter().copied().for_each(&mut widen);
+        let mut moved = vec![];
+        for &(index, previous_times) in &moved_tasks {
+            let position = state.positions[index];
+            let times = (state.start_times[index], state.end_times[index]);
+            if let Some(previous_times) = previous_times {
+                state.count_times(previous_times, -1, &mut scheduler.scheduled_tasks_time_nodes);
+                state.end_times_by_position.remove(&(previous_times.1, Reverse(position)));
+                widen(previous_times);
+                if previous_times.0 != times.0 {
+                    moved.push((position, MovedTask { name: tasks[index].name.clone(), start_time: (previous_times.0, times.0) }));
+                }
+            }
+            state.count_times(times, 1, &mut scheduler.scheduled_tasks_time_nodes);
+            state.end_times_by_position.insert((times.1, Reverse(position)));
+            widen(times);
+            let scheduled_task = &mut scheduler.scheduled_tasks[position];
+            (scheduled_task.start_time, scheduled_task.end_time) = times;
+        }
+        moved.sort_unstable_by_key(|&(position, _)| position);
+
+        // in reverse topological order, so the tails of the dependents are final before a task takes them up
+        let mut touched: HashSet<usize> = HashSet::new();
+        let mut queue: BTreeSet<Reverse<usize>> = changes.tail_changes.iter().map(|&index| Reverse(state.positions[index])).collect();
+        while let Some(Reverse(position)) = queue.pop_first() {
+            let index = graph.topological_order[position].index();
+            let tail = state.tail(tasks, index);
+            touched.insert(index);
+            if tail != state.tails[index] || changes.added.contains(&index) {
+                state.tails[index] = tail;
+                queue.extend(graph.dependencies[index].iter().map(|dependency| Reverse(state.positions[dependency.index()])));
+            }
+        }
+        // a slack depends on the times of the task and of its dependents
+        let slack_changes = touched
+            .iter()
+            .copied()
+            .chain(moved_tasks.iter().flat_map(|&(index, _)| once(index).chain(graph.dependencies[index].iter().map(|id| id.index()))))
+            .collect::<HashSet<usize>>();
+        for &index in &slack_changes {
+            state.slacks[index] = state.slack(tasks, index);
+        }
+
+        let previous_makespan = scheduler.last_task.end_time;
+        scheduler.last_task = match state.end_times_by_position.last() {
+            Some(&(end_time, Reverse(position))) if end_time > 0 => scheduler.scheduled_tasks[position].clone(),
+            _ => Task::default(),
+        };
+        let makespan = scheduler.last_task.end_time;
+        // the latest times of every task count back from the minimum duration
+        if makespan != previous_makespan {
+            for index in 0..tasks.len() {
+                scheduler.timings[state.positions[index]] = state.timing(tasks, index, makespan);
+            }
+        } else {
+            for &index in &slack_changes {
+                scheduler.timings[state.positions[index]] = state.timing(tasks, index, makespan);
+            }
+        }
+
+        if let Some((from, to)) = window {
+            update_parallelism(scheduler, state, from, to);
+        }
+        scheduler.assemble_critical_path(&state.positions);
+
+        (rescheduled, moved.into_iter().map(|(_, task)| task).collect())
+    }
+
+    pub fn schedule(&self) -> Schedule {
+        self.scheduler.to_schedule()
+    }
+}
+
+// Only the intervals between the first and the last time that changed are counted again: before them the same tasks
+// run, and after them too, as the tasks that moved started and ended within them.
+fn update_parallelism(scheduler: &mut Scheduler, state: &IncrementalState, from: TimeMoment, to: TimeMoment) {
+    let profile = &scheduler.parallelism_profile;
+    let first = profile.partition_point(|interval| interval.to < from);
+    let last = profile.partition_point(|interval| interval.from <= to);
+    let (first_node, mut running) = match first.checked_sub(1).map(|before| profile[before]) {
+        Some(interval) => (interval.to, interval.tasks as isize),
+        None => (state.time_nodes.keys().next().copied().unwrap_or(0), 0),
+    };
+    let last_node = profile.get(last).map_or_else(|| state.time_nodes.keys().next_back().copied().unwrap_or(0), |interval| interval.from);
+
+    let time_nodes: Vec<TimeMoment> = state.time_nodes.range(first_node..=last_node).map(|(&time, _)| time).collect();
+    let intervals: Vec<ParallelismInterval> = time_nodes
+        .windows(2)
+        .map(|node| {
+            running += state.running_changes.get(&node[0]).copied().unwrap_or(0);
+            ParallelismInterval { from: node[0], to: node[1], tasks: running as usize }
+        })
+        .collect();
+    let most_tasks = intervals.iter().map(|interval| interval.tasks).max().unwrap_or(0);
+    let most_tasks_replaced = scheduler.parallelism_profile.splice(first..last, intervals).map(|interval| interval.tasks).max().unwrap_or(0);
+
+    if most_tasks >= scheduler.max_parallelism {
+        scheduler.max_parallelism = most_tasks;
+    } else if most_tasks_replaced == scheduler.max_parallelism {
+        scheduler.max_parallelism = scheduler.parallelism_profile.iter().map(|interval| interval.tasks).max().unwrap_or(0);
+    }
+}
+
+pub fn build_incremental_scheduler() -> IncrementalScheduler {
+    IncrementalScheduler {
+        policy: Default::default(),
+        job: Default::default(),
+        scheduler: build_scheduler(vec![]),
+        state: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::parsers::parse_job;
+    use crate::scheduler::schedule_job;
+
+    #[test]
+    fn reschedule_changed_tasks_and_their_dependents() {
+        let mut scheduler = build_incremental_scheduler();
+        let update = scheduler.update(parse_job("A(1)\nB(2) after [A]\nC(1) after [A]\nD(1) after [C]\nE(4)\n").unwrap()).unwrap();
+        assert_eq!(update.rescheduled, 5);
+        assert_eq!(update.diff.critical_path, (vec![], vec!["E".to_string()]));
+
+        let job = parse_job("A(1)\nB(2) after [A]\nC(4) after [A]\nD(1) after [C]\nE(4)\n").unwrap();
+        let update = scheduler.update(job.clone()).unwrap();
+        // only C and D depending on it are scheduled again
+        assert_eq!(update.rescheduled, 2);
+        assert_eq!(update.diff.moved, vec![MovedTask { name: "D".to_string(), start_time: (2, 5) }]);
+        assert_eq!(update.diff.makespan, (4, 6));
+        assert!(update.diff.critical_path_changed());
+        assert_eq!(update.print(), r#"Rescheduled 2 of 5 tasks
+Changed: C duration 1 -> 4
+Moved: D 2 -> 5 (+3) (critical)
+Minimum: 4 -> 6
+Parallelism: 3
+Critical path changed: E => A->C->D"#);
+        assert_eq!(scheduler.schedule(), schedule_job(job).unwrap());
+    }
+
+    #[test]
+    fn reschedule_after_removing_a_task() {
+        let mut scheduler = build_incremental_scheduler();
+        scheduler.update(parse_job("A(3)\nB(1)\nC(1) after [A, B]\n").unwrap()).unwrap();
+
+        let job = parse_job("B(1)\nC(1) after [B]\n").unwrap();
+        let update = scheduler.update(job.clone()).unwrap();
+        assert_eq!(update.diff.tasks.removed, vec!["A"]);
+        assert_eq!(update.rescheduled, 1);
+        assert_eq!(update.diff.moved, vec![MovedTask { name: "C".to_string(), start_time: (3, 1) }]);
+        assert_eq!(scheduler.schedule(), schedule_job(job).unwrap());
+    }
+
+    #[test]
+    fn keep_previous_version_after_error() {
+        let mut scheduler = build_incremental_scheduler();
+        scheduler.update(parse_job("A(1)\nB(1) after [A]\n").unwrap()).unwrap();
+
+        assert!(scheduler.update(parse_job("A(1) after [B]\nB(1) after [A]\n").unwrap()).is_err());
+        let update = scheduler.update(parse_job("A(1)\nB(2) after [A]\n").unwrap()).unwrap();
+        assert_eq!(update.diff.tasks.changed.len(), 1);
+        assert_eq!(update.diff.makespan, (2, 3));
+    }
+
+    #[test]
+    fn reschedule_only_the_last_task_of_a_chain() {
+        let mut scheduler = build_incremental_scheduler();
+        scheduler.update(parse_job("A(1)\nB(1) after [A]\nC(1) after [B]\nD(1) after [C]\n").unwrap()).unwrap();
+
+        let job = parse_job("A(1)\nB(1) after [A]\nC(1) after [B]\nD(3) after [C]\n").unwrap();
+        let update = scheduler.update(job.clone()).unwrap();
+        assert_eq!(update.rescheduled, 1);
+        assert_eq!(update.diff.moved, vec![]);
+        assert_eq!(update.diff.makespan, (4, 6));
+        assert_eq!(scheduler.schedule(), schedule_job(job).unwrap());
+    }
+
+    #[test]
+    fn reschedule_as_the_whole_job_after_every_change() {
+        let jobs = [
+            "A(2)\nB(3) after [A]\nC(1) after [start(A)+1]\nD(0) after [B, C]\nE(2) after [finish(C)]\n",
+            // longer and shorter tasks
+            "A(4)\nB(1) after [A]\nC(1) after [start(A)+1]\nD(0) after [B, C]\nE(2) after [finish(C)]\n",
+            // another kind and lag of a dependency
+            "A(4)\nB(1) after [A]\nC(1) after [finish(A)-2]\nD(0) after [B, C]\nE(2) after [finish(C)]\n",
+            // a task added between the others, which another task depends on
+            "A(4)\nF(5)\nB(1) after [A]\nC(1) after [finish(A)-2]\nD(0) after [B, C, F]\nE(2) after [finish(C)]\n",
+            // the tasks in another order
+            "F(5)\nA(4)\nB(1) after [A]\nC(1) after [finish(A)-2]\nD(0) after [B, C, F]\nE(2) after [finish(C)]\n",
+            // a new dependency
+            "F(5)\nA(4)\nB(1) after [A]\nC(1) after [finish(A)-2, start(F)+3]\nD(0) after [B, C, F]\nE(2) after [finish(C)]\n",
+            // a task removed
+            "A(4)\nB(1) after [A]\nC(1) after [finish(A)-2]\nD(0) after [B, C]\nE(2) after [finish(C)]\n",
+            "A(0)\nB(0) after [A]\nC(0) after [finish(A)-2]\nD(0) after [B, C]\nE(0) after [finish(C)]\n",
+            "A(1)\nB(0) after [A]\nC(0) after [finish(A)-2]\nD(0) after [B, C]\nE(0) after [finish(C)]\n",
+        ];
+        let mut scheduler = build_incremental_scheduler();
+        for job in jobs {
+            let job = parse_job(job).unwrap();
+            scheduler.update(job.clone()).unwrap();
+            assert_eq!(scheduler.schedule(), schedule_job(job).unwrap());
+        }
+    }
+
+    #[test]
+    fn reschedule_job_with_resources_in_full() {
+        let mut scheduler = build_incremental_scheduler();
+        scheduler.update(parse_job("capacity [cpu:1]\nA(1) uses [cpu:1]\nB(1) uses [cpu:1]\nC(1)\n").unwrap()).unwrap();
+
+        let job = parse_job("capacity [cpu:1]\nA(2) uses [cpu:1]\nB(1) uses [cpu:1]\nC(1)\n").unwrap();
+        let update = scheduler.update(job.clone()).unwrap();
+        assert_eq!((update.rescheduled, update.tasks), (3, 3));
+        assert_eq!(update.diff.moved, vec![MovedTask { name: "B".to_string(), start_time: (1, 2) }]);
+        assert_eq!(scheduler.schedule(), schedule_job(job).unwrap());
+
+        let job = parse_job("A(2)\nB(1)\nC(1)\n").unwrap();
+        scheduler.update(job.clone()).unwrap();
+        assert_eq!(scheduler.schedule(), schedule_job(job).unwrap());
+        let update = scheduler.update(parse_job("A(2)\nB(1)\nC(3)\n").unwrap()).unwrap();
+        assert_eq!(update.rescheduled, 1);
+    }
+}
@@ -1 +1,3 @@
+pub mod diagnostic;
+pub mod include;
 pub mod parsers;
@@ -0,0 +1,367 @@
+use nom_supreme::{
+    error::{
+        BaseErrorKind,
+        ErrorTree,
+        Expectation,
+        StackContext,
+    },
+    final_parser::Location,
+};
+
+
+const KEYWORDS: [&str; 12] = ["after", "uses", "priority", "run", "start", "finish", "capacity", "include", "name", "owner", "description", "as"];
+
+// A syntax error as rustc reports it: a message, the line of the job file with a caret under the error and an
+// optional hint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+    pub source_line: String,
+    // the number of carets, the length of the word at the location if there is one
+    pub length: usize,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn print(&self, file: &str) -> String {
+        let width = self.line.to_string().len();
+        let before: String = self.source_line
+            .get(..self.column - 1)
+            .unwrap_or(&self.source_line)
+            .chars()
+            .map(|character| if character == '\t' { '\t' } else { ' ' })
+            .collect();
+        let column = character_column(&self.source_line, self.column);
+
+        let mut lines = vec![
+            format!("error: {}", self.message),
+            format!("{:width$}--> {}:{}:{}", "", file, self.line, column, width = width),
+            format!("{:width$} |", "", width = width),
+            format!("{} | {}", self.line, self.source_line),
+            format!("{:width$} | {}{}", "", before, "^".repeat(self.length), width = width),
+        ];
+        if let Some(help) = &self.help {
+            lines.push(format!("{:width$} = help: {}", "", help, width = width));
+        }
+        lines.join("\n")
+    }
+}
+
+// nom-supreme counts the column in bytes, the column of a location in the line counts characters instead
+pub fn character_column(source_line: &str, column: usize) -> usize {
+    source_line.get(..column - 1).map_or(column, |before| before.chars().count() + 1)
+}
+
+fn source_line(input: &str, line: usize) -> &str {
+    input.lines().nth(line - 1).unwrap_or_default()
+}
+
+// one base error with the contexts around it, innermost first
+struct Leaf<'e> {
+    location: Location,
+    kind: &'e BaseErrorKind,
+    contexts: Vec<(Location, &'static str)>,
+}
+
+fn collect_leaves<'e>(error: &'e ErrorTree<Location>, contexts: &[(Location, &'static str)], leaves: &mut Vec<Leaf<'e>>) {
+    match error {
+        ErrorTree::Base { location, kind } => leaves.push(Leaf { location: *location, kind, contexts: contexts.to_vec() }),
+        ErrorTree::Stack { base, contexts: stack } => {
+            let contexts: Vec<(Location, &'static str)> = stack
+                .iter()
+                .filter_map(|(location, context)| match context {
+                    StackContext::Context(context) => Some((*location, *context)),
+                    StackContext::Kind(_) => None,
+                })
+                .chain(contexts.iter().copied())
+                .collect();
+            collect_leaves(base, &contexts, leaves)
+        }
+        ErrorTree::Alt(alternatives) => {
+            for alternative in alternatives {
+                collect_leaves(alternative, contexts, leaves);
+            }
+        }
+    }
+}
+
+// what the parser contexts are called in messages; contexts around a whole task or job are left out
+fn describe_context(context: &str) -> Option<&'static str> {
+    match context {
+        "task-name" => Some("task name"),
+        "duration" => Some("duration"),
+        "unit" => Some("unit"),
+        "estimate" => Some("three-point estimate"),
+        "dependencies" => Some("dependencies"),
+        "dependencies-array" => Some("dependency list"),
+        "dependency" => Some("dependency"),
+        "priority" => Some("priority"),
+        "resource" => Some("resource"),
+        "resources-array" => Some("resource list"),
+        "capacities" => Some("capacity line"),
+        "metadata" => Some("metadata header"),
+        "include" => Some("include directive"),
+        "command" => Some("command"),
+        _ => None,
+    }
+}
+
+fn describe_expectation(expectation: &Expectation) -> String {
+    match expectation {
+        Expectation::Char(character) => format!("'{}'", character),
+        Expectation::Tag(tag) => format!("\"{}\"", tag),
+        Expectation::Alpha => "a letter".to_string(),
+        Expectation::Digit => "a digit".to_string(),
+        Expectation::AlphaNumeric => "a letter or digit".to_string(),
+        Expectation::Space => "a space".to_string(),
+        Expectation::Multispace => "whitespace".to_string(),
+        Expectation::Eof => "the end of the file".to_string(),
+        Expectation::CrLf => "a line ending".to_string(),
+        expectation => expectation.to_string(),
+    }
+}
+
+// a, b or c
+fn join_alternatives(items: &[String]) -> String {
+    match items.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, others)) => format!("{} or {}", others.join(", "), last),
+        None => String::new(),
+    }
+}
+
+// the number of insertions, deletions, substitutions and swaps of adjacent characters turning one word into the other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    distances[0] = (0..=b.len()).collect();
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (distances[i - 1][j] + 1).min(distances[i][j - 1] + 1).min(distances[i - 1][j - 1] + substitution);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+fn typo_hint(word: &str, expected_tags: &[&str]) -> Option<String> {
+    if word.len() < 3 {
+        return None;
+    }
+    let lowercase = word.to_lowercase();
+    expected_tags
+        .iter()
+        .chain(KEYWORDS.iter())
+        .filter(|keyword| **keyword != word)
+        .map(|keyword| (edit_distance(&lowercase, keyword), *keyword))
+        .filter(|(distance, keyword)| *distance <= if keyword.len() <= 4 { 1 } else { 2 })
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, keyword)| format!("did you mean `{}`?", keyword))
+}
+
+// The diagnostic of the error furthest into the input, with the expectations of all alternatives failing there.
+pub fn diagnose(input: &str, error: &ErrorTree<Location>) -> Diagnostic {
+    let mut leaves = vec![];
+    collect_leaves(error, &[], &mut leaves);
+    let furthest = leaves
+        .iter()
+        .map(|leaf| (leaf.location.line, leaf.location.column))
+        .max()
+        .unwrap_or((1, 1));
+    let leaves: Vec<&Leaf> = leaves.iter().filter(|leaf| (leaf.location.line, leaf.location.column) == furthest).collect();
+    let (line, column) = furthest;
+
+    let mut expected: Vec<String> = vec![];
+    let mut expected_tags = vec![];
+    for leaf in &leaves {
+        if let BaseErrorKind::Expected(expectation) = leaf.kind {
+            let description = describe_expectation(expectation);
+            if !expected.contains(&description) {
+                expected.push(description);
+            }
+            if let Expectation::Tag(tag) = expectation {
+                expected_tags.push(*tag);
+            }
+        }
+    }
+    let contexts = leaves.first().map(|leaf| leaf.contexts.clone()).unwrap_or_default();
+    let description = contexts.iter().find_map(|(_, context)| describe_context(context));
+    // the innermost list or duration that is not closed, and where it opens
+    let opening = contexts.iter().find_map(|(location, context)| match *context {
+        "dependencies-array" | "resources-array" => Some(("']'", *location)),
+        "duration" => Some(("')'", Location { column: location.column.saturating_sub(1).max(1), ..*location })),
+        _ => None,
+    });
+
+    let message = match opening {
+        Some((closing, location)) if expected.iter().any(|expectation| expectation == closing) && description.is_some() => format!(
+            "expected {} to close {} opened at {}:{}",
+            closing,
+            description.unwrap_or_default(),
+            location.line,
+            character_column(source_line(input, location.line), location.column),
+        ),
+        _ if !expected.is_empty() => match description {
+            Some(description) => format!("expected {} in the {}", join_alternatives(&expected), description),
+            None => format!("expected {}", join_alternatives(&expected)),
+        },
+        _ => match (leaves.first().map(|leaf| leaf.kind), description) {
+            (Some(BaseErrorKind::External(error)), _) => error.to_string(),
+            (_, Some(description)) => format!("invalid {}", description),
+            _ => "invalid syntax".to_string(),
+        },
+    };
+
+    let source_line = source_line(input, line).to_string();
+    let rest = source_line.get(column - 1..).unwrap_or_default();
+    let word_length = rest.find(|character: char| !character.is_alphanumeric()).unwrap_or(rest.len());
+    let word = &rest[..word_length];
+    let before = source_line.get(..column - 1).unwrap_or_default();
+
+    let help = typo_hint(word, &expected_tags).or_else(|| {
+        if description == Some("three-point estimate") && expected.is_empty() {
+            Some("an estimate is optimistic..likely..pessimistic, each at least the one before, e.g. 2..3..8".to_string())
+        } else if description == Some("task name") && expected.iter().any(|expectation| expectation == "'('") {
+            Some("a task name is directly followed by its duration between parentheses, e.g. A(3)".to_string())
+        } else if description == Some("duration") && rest.starts_with(char::is_alphabetic) {
+            Some("the unit of a duration is ms, s, m or min, h or d".to_string())
+        } else if expected_tags.contains(&"after") && !before.is_empty() && before.trim().is_empty() {
+            Some("a task should start at the beginning of a line".to_string())
+        } else {
+            None
+        }
+    });
+
+    Diagnostic {
+        message,
+        line,
+        column,
+        source_line,
+        length: word_length.max(1),
+        help,
+    }
+}
+
+// The error as the assignment asks for it: Error: line 2, column 6, kind: expected "after", one line per alternative.
+pub fn print_compatible(file: Option<&str>, input: &str, error: &ErrorTree<Location>) -> String {
+    let print_base = |location: &Location, kind: &BaseErrorKind| {
+        let Location { line, column } = location;
+        let column = character_column(source_line(input, *line), *column);
+        // nom-supreme writes the message of an error of the job file itself on the next line
+        let kind = match kind {
+            BaseErrorKind::External(error) => error.to_string(),
+            kind => kind.to_string(),
+        };
+        match file {
+            Some(file) => format!("Error: {} line {}, column {}, kind: {}", file, line, column, kind),
+            None => format!("Error: line {}, column {}, kind: {}", line, column, kind),
+        }
+    };
+
+    match error {
+        ErrorTree::Stack { base, contexts: _ } => match base.as_ref() {
+            ErrorTree::Base { location, kind } => print_base(location, kind),
+            ErrorTree::Alt(bases) => bases
+                .iter()
+                .rev()
+                .filter_map(|base| match base {
+                    ErrorTree::Base { location, kind } => Some(print_base(location, kind)),
+                    _ => None,
+                })
+                .collect::<Vec<String>>()
+                .join("\n"),
+            _ => "Error".to_string(),
+        },
+        ErrorTree::Base { location, kind } => print_base(location, kind),
+        error => format!("Error: {:#?}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::parsers::parse_job;
+
+    fn diagnose_job(input: &str) -> Diagnostic {
+        diagnose(input, &parse_job(input).unwrap_err())
+    }
+
+    #[test]
+    fn diagnose_typo() {
+        let input = "A(1)\nB(1) afer [A]\n";
+        let diagnostic = diagnose_job(input);
+
+        assert_eq!(diagnostic.message, r#"expected "after" in the dependencies"#);
+        assert_eq!(diagnostic.print("test/invalidsyntax.tasks.in"), r#"error: expected "after" in the dependencies
+ --> test/invalidsyntax.tasks.in:2:6
+  |
+2 | B(1) afer [A]
+  |      ^^^^
+  = help: did you mean `after`?"#);
+    }
+
+    #[test]
+    fn diagnose_unclosed_list() {
+        let diagnostic = diagnose_job("A(1)\nB(1) after [A\nC(1)\n");
+        assert_eq!(diagnostic.message, "expected ']' to close dependency list opened at 2:12");
+        assert_eq!((diagnostic.line, diagnostic.column), (3, 1));
+
+        let diagnostic = diagnose_job("A(1 after [B]\n");
+        assert_eq!(diagnostic.message, "expected ')' to close duration opened at 1:2");
+    }
+
+    #[test]
+    fn diagnose_with_hints() {
+        assert_eq!(diagnose_job("A (1)\n").help.unwrap(), "a task name is directly followed by its duration between parentheses, e.g. A(3)");
+        assert_eq!(diagnose_job("A(3w)\n").help.unwrap(), "the unit of a duration is ms, s, m or min, h or d");
+        assert_eq!(diagnose_job("A(1)\n  B(1)\n").help.unwrap(), "a task should start at the beginning of a line");
+        assert_eq!(diagnose_job("A(1) use [gpu:1]\n").help.unwrap(), "did you mean `uses`?");
+        assert_eq!(diagnose_job("A(4..3..8)\n").message, "invalid three-point estimate");
+    }
+
+    #[test]
+    fn print_caret_under_tabs() {
+        let diagnostic = Diagnostic {
+            message: "expected ')' in the duration".to_string(),
+            line: 12,
+            column: 4,
+            source_line: "\tA(1".to_string(),
+            length: 1,
+            help: None,
+        };
+        assert_eq!(diagnostic.print("-"), "error: expected ')' in the duration\n  --> -:12:4\n   |\n12 | \tA(1\n   | \t  ^");
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("afer", "after"), 1);
+        assert_eq!(edit_distance("atfer", "after"), 1);
+        assert_eq!(edit_distance("uses", "after"), 4);
+    }
+
+    #[test]
+    fn print_compatible_error() {
+        let input = "A(1)\nB(1) afer [A]\n";
+        let error = parse_job(input).unwrap_err();
+        assert_eq!(print_compatible(None, input, &error), r#"Error: line 2, column 6, kind: expected "after""#);
+        assert_eq!(print_compatible(Some("build.tasks.in"), input, &error), r#"Error: build.tasks.in line 2, column 6, kind: expected "after""#);
+    }
+
+    #[test]
+    fn count_columns_in_characters() {
+        assert_eq!(character_column("größer x", 10), 8);
+        assert_eq!(character_column("abc", 3), 3);
+        let input = "A(1) run \"größer\" afer [B]\n";
+        let error = parse_job(input).unwrap_err();
+        assert_eq!(print_compatible(None, input, &error).lines().next().unwrap(), r#"Error: line 1, column 19, kind: expected "after""#);
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::HashSet;
+use std::fs;
+use std::iter::once;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use nom_supreme::{
+    error::ErrorTree,
+    final_parser::Location,
+};
+use crate::error::IncludeError;
+use crate::input::parsers::parse_job_with_recovery;
+use crate::task::{
+    Job,
+    SourceLocation,
+};
+
+
+// A job file parsed once, with its included files, and then merged with the others into the job. A file with syntax
+// errors keeps the tasks that did parse, so the job can still be checked.
+#[derive(Debug)]
+pub struct JobFile {
+    pub path: String,
+    // prefix of the task names, build:: for a file included as build
+    pub namespace: String,
+    pub text: String,
+    pub job: Job,
+    pub syntax_errors: Vec<ErrorTree<Location>>,
+    // the names of the tasks including the namespace, in file order
+    pub task_names: Vec<String>,
+}
+
+// A job file and all job files it includes, directly or through other included files, in the order they are included.
+#[derive(Debug, Default)]
+pub struct JobFiles {
+    pub files: Vec<JobFile>,
+}
+
+impl JobFiles {
+    // The paths on the stack are the files being included, to detect a file that includes itself. The loaded files
+    // are the canonical paths and namespaces so far: a file included twice in the same namespace, as in a diamond,
+    // is loaded once.
+    fn load(
+        &mut self,
+        path: &Path,
+        namespace: String,
+        text: String,
+        stack: &mut Vec<(PathBuf, String)>,
+        loaded: &mut HashSet<(PathBuf, String)>,
+    ) -> Result<(), IncludeError> {
+        let display = path.display().to_string();
+        let (job, syntax_errors) = parse_job_with_recovery(&text);
+        let task_names = job.tasks.iter().map(|task| format!("{}{}", namespace, task.name)).collect();
+        let includes = job.includes.clone();
+        let is_root = self.files.is_empty();
+        self.files.push(JobFile { path: display.clone(), namespace: namespace.clone(), text, job, syntax_errors, task_names });
+
+        // included paths are relative to the directory of the including file
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        for include in includes {
+            let included_path = directory.join(&include.path);
+            let included = included_path.display().to_string();
+            let location = SourceLocation { file: (!is_root).then(|| display.clone()), ..include.location };
+            let read_error = |error: std::io::Error| IncludeError::Read {
+                file: included.clone(),
+                message: error.to_string(),
+                location: Some(location.clone()),
+            };
+
+            let canonical_path = fs::canonicalize(&included_path).map_err(read_error)?;
+            if let Some(position) = stack.iter().position(|(path, _)| *path == canonical_path) {
+                let files = stack[position..].iter().map(|(_, file)| file.clone()).chain(once(included)).collect();
+                return Err(IncludeError::Cycle { files, location });
+            }
+            let namespace = match &include.namespace {
+                Some(name) => format!("{}{}::", namespace, name),
+                None => namespace.clone(),
+            };
+            if !loaded.insert((canonical_path.clone(), namespace.clone())) {
+                continue;
+            }
+            let text = fs::read_to_string(&included_path).map_err(read_error)?;
+
+            stack.push((canonical_path, included));
+            self.load(&included_path, namespace, text, stack, loaded)?;
+            stack.pop();
+        }
+
+        Ok(())
+    }
+
+    // The job of all files together. The metadata comes from the first file, a capacity declared in several files
+    // must have the same value in each, and a dependency names a task in the same namespace if there is one.
+    pub fn job(&self) -> Result<Job, IncludeError> {
+        let task_names: HashSet<&str> = self.files
+            .iter()
+            .flat_map(|file| &file.task_names)
+            .map(String::as_str)
+            .collect();
+        let time_scale = self.files.iter().map(|file| file.job.time_scale).max().unwrap_or_default();
+
+        let mut merged_job = Job { time_scale, ..Default::default() };
+        // where the capacity of each resource is first declared
+        let mut capacity_locations: Vec<Option<SourceLocation>> = vec![];
+        for (index, file) in self.files.iter().enumerate() {
+            let job = file.job.clone();
+            let capacity_location = job.capacity_location.map(|location| SourceLocation {
+                file: (index > 0).then(|| file.path.clone()),
+                ..location
+            });
+            if index == 0 {
+                merged_job.metadata = job.metadata;
+                merged_job.includes = job.includes;
+                // a capacity declared twice in the including file is left for the job check to report
+                capacity_locations = vec![capacity_location.clone(); job.capacities.len()];
+                merged_job.capacities = job.capacities;
+                merged_job.capacity_location = capacity_location;
+            } else {
+                for (resource, capacity) in job.capacities {
+                    match merged_job.capacities.iter().position(|(declared, _)| *declared == resource) {
+                        Some(position) if merged_job.capacities[position].1 != capacity => {
+                            return Err(IncludeError::CapacityConflict {
+                                resource,
+                                capacities: vec![merged_job.capacities[position].1, capacity],
+                                locations: vec![capacity_locations[position].clone(), capacity_location],
+                            });
+                        }
+                        Some(_) => {}
+                        None => {
+                            merged_job.capacities.push((resource, capacity));
+                            capacity_locations.push(capacity_location.clone());
+                        }
+                    }
+                }
+            }
+
+            for (mut task, name) in job.tasks.into_iter().zip(&file.task_names) {
+                task.name = name.clone();
+                // a file with whole time units among files with finer ticks counts in seconds, as a number without unit does
+                task.rescale(time_scale.ticks_per_unit(), job.time_scale.ticks_per_unit());
+                for dependency in task.dependencies.iter_mut() {
+                    let qualified_name = format!("{}{}", file.namespace, dependency.name);
+                    if task_names.contains(qualified_name.as_str()) {
+                        dependency.name = qualified_name;
+                    }
+                }
+                if index > 0 {
+                    let dependency_locations = task.dependencies.iter_mut().map(|dependency| &mut dependency.location);
+                    for location in once(&mut task.location).chain(dependency_locations).flatten() {
+                        location.file = Some(file.path.clone());
+                    }
+                }
+                merged_job.tasks.push(task);
+            }
+        }
+
+        Ok(merged_job)
+    }
+}
+
+// Reads the job files included by the job file at the path with the text, stdin when the path is -.
+pub fn load_job_files(path: &str, text: String) -> Result<JobFiles, IncludeError> {
+    let path = Path::new(path);
+    let canonical_path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut stack = vec![(canonical_path.clone(), path.display().to_string())];
+    let mut loaded = HashSet::from([(canonical_path, String::new())]);
+    let mut job_files = JobFiles::default();
+    job_files.load(path, String::new(), text, &mut stack, &mut loaded)?;
+
+    Ok(job_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::Dependency;
+    use crate::time::TimeScale;
+
+    fn write_files(directory: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("schedule_tasks_{}_{}", directory, std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        for (name, text) in files {
+            fs::write(directory.join(name), text).unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn load_job_with_included_files() {
+        let directory = write_files("include", &[
+            ("release.tasks.in", "capacity [gpu:2]\ninclude \"build.tasks.in\" as build\nrelease(1) after [build::test]\n"),
+            ("build.tasks.in", "capacity [mem:8, gpu:2]\ncompile(1.5) uses [gpu:1]\ntest(2) after [compile, setup]\n"),
+        ]);
+        let root = directory.join("release.tasks.in");
+        let root = root.to_str().unwrap();
+        let job_files = load_job_files(root, fs::read_to_string(root).unwrap()).unwrap();
+        let job = job_files.job().unwrap();
+
+        let names: Vec<&str> = job.tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, vec!["release", "build::compile", "build::test"]);
+        assert_eq!(job.time_scale, TimeScale::Thousandths);
+        assert_eq!(job.tasks[0].duration, 1000);
+        assert_eq!(job.tasks[0].dependencies, vec![Dependency::finish_to_start("build::test")]);
+        // setup is not a task of the build namespace, so it is left for a task of that name elsewhere
+        assert_eq!(job.tasks[2].dependencies, vec![Dependency::finish_to_start("build::compile"), Dependency::finish_to_start("setup")]);
+        assert_eq!(job.capacities, vec![("gpu".to_string(), 2), ("mem".to_string(), 8)]);
+        assert_eq!(job.tasks[0].location, Some(SourceLocation::new(3, 1)));
+        assert_eq!(job.tasks[2].location, Some(SourceLocation {
+            file: Some(directory.join("build.tasks.in").display().to_string()),
+            ..SourceLocation::new(3, 1)
+        }));
+    }
+
+    #[test]
+    fn load_file_included_twice_in_the_same_namespace_once() {
+        let directory = write_files("include_diamond", &[
+            ("release.tasks.in", "include \"build.tasks.in\"\ninclude \"docs.tasks.in\"\ninclude \"docs.tasks.in\" as docs\nrelease(1) after [compile]\n"),
+            ("build.tasks.in", "include \"common.tasks.in\"\ncompile(2) after [setup]\n"),
+            ("docs.tasks.in", "include \"common.tasks.in\"\nmanual(1) after [setup]\n"),
+            ("common.tasks.in", "setup(1)\n"),
+        ]);
+        let root = directory.join("release.tasks.in");
+        let root = root.to_str().unwrap();
+        let job_files = load_job_files(root, fs::read_to_string(root).unwrap()).unwrap();
+        let job = job_files.job().unwrap();
+
+        let names: Vec<&str> = job.tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, vec!["release", "compile", "setup", "manual", "docs::manual", "docs::setup"]);
+        assert_eq!(job_files.files.len(), 6);
+    }
+
+    #[test]
+    fn detect_capacity_conflict() {
+        let directory = write_files("include_capacity", &[
+            ("release.tasks.in", "capacity [gpu:2]\ninclude \"build.tasks.in\"\nrelease(1)\n"),
+            ("build.tasks.in", "capacity [gpu:4]\ncompile(1)\n"),
+        ]);
+        let root = directory.join("release.tasks.in");
+        let root = root.to_str().unwrap();
+        let error = load_job_files(root, fs::read_to_string(root).unwrap()).unwrap().job().unwrap_err();
+
+        let build = directory.join("build.tasks.in").display().to_string();
+        assert_eq!(error.to_string(), format!("capacity of gpu is declared as 2 and as 4 (line 1, column 1; {} line 1, column 1)", build));
+    }
+
+    #[test]
+    fn detect_include_cycle() {
+        let directory = write_files("include_cycle", &[
+            ("a.tasks.in", "include \"b.tasks.in\"\nA(1)\n"),
+            ("b.tasks.in", "include \"a.tasks.in\"\nB(1)\n"),
+        ]);
+        let root = directory.join("a.tasks.in");
+        let root = root.to_str().unwrap();
+        let error = load_job_files(root, fs::read_to_string(root).unwrap()).unwrap_err();
+
+        let a = directory.join("a.tasks.in").display().to_string();
+        let b = directory.join("b.tasks.in").display().to_string();
+        assert_eq!(error.to_string(), format!("include cycle {} -> {} -> {} ({} line 1, column 1)", a, b, a, b));
+    }
+
+    #[test]
+    fn included_file_does_not_exist() {
+        let directory = write_files("include_missing", &[("a.tasks.in", "name: Job\ninclude \"missing.tasks.in\"\nA(1)\n")]);
+        let root = directory.join("a.tasks.in");
+        let root = root.to_str().unwrap();
+        let error = load_job_files(root, fs::read_to_string(root).unwrap()).unwrap_err();
+
+        assert!(matches!(error, IncludeError::Read { location: Some(SourceLocation { line: 2, column: 1, .. }), .. }));
+    }
+}
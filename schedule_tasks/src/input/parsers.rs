@@ -1,3 +1,4 @@
+use std::iter::once;
 use nom::{
     Parser,
     IResult,
@@ -5,22 +6,74 @@ use nom::{
     character::complete::{
         alphanumeric1,
         char,
-        multispace0,
         multispace1,
+        none_of,
+        not_line_ending,
         space0,
+        space1,
         digit1,
         line_ending,
-    }};
-use nom::combinator::eof;
+    },
+    bytes::complete::{
+        escaped_transform,
+        take_till1,
+        take_while_m_n,
+    },
+    error::{
+        ErrorKind,
+        FromExternalError,
+    },
+    multi::{
+        many0,
+        many0_count,
+        many1_count,
+        separated_list1,
+    },
+};
+use nom::combinator::{
+    eof,
+    recognize,
+    value,
+    verify,
+};
 use nom_supreme::{
     parser_ext::ParserExt,
     error::ErrorTree,
-    final_parser::final_parser,
+    final_parser::{
+        final_parser,
+        ExtractContext,
+        Location,
+    },
     multi::collect_separated_terminated,
     tag::complete::tag,
     parse_from_str,
 };
-use crate::task::Task;
+use crate::error::{
+    DuplicateMetadataError,
+    TimeError,
+};
+use crate::task::{
+    Dependency,
+    DependencyKind,
+    Duration,
+    Estimate,
+    Include,
+    Job,
+    Lag,
+    Metadata,
+    Priority,
+    ResourceAmount,
+    SourceLocation,
+    Task,
+    TimeMoment,
+};
+use crate::time::{
+    TimeScale,
+    MILLISECONDS_PER_DAY,
+    MILLISECONDS_PER_HOUR,
+    MILLISECONDS_PER_MINUTE,
+    MILLISECONDS_PER_SECOND,
+};
 
 
 fn parse_name(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
@@ -30,15 +83,117 @@ fn parse_name(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
         .parse(input)
 }
 
-fn parse_duration(input: &str) -> IResult<&str, usize, ErrorTree<&str>> {
-    parse_from_str(digit1)
+fn parse_unit(input: &str) -> IResult<&str, TimeMoment, ErrorTree<&str>> {
+    alt((
+        tag("ms").value(1),
+        tag("min").value(MILLISECONDS_PER_MINUTE),
+        tag("m").value(MILLISECONDS_PER_MINUTE),
+        tag("s").value(MILLISECONDS_PER_SECOND),
+        tag("h").value(MILLISECONDS_PER_HOUR),
+        tag("d").value(MILLISECONDS_PER_DAY),
+    ))
+        .context("unit")
+        .parse(input)
+}
+
+// 3, 1.5, 90s, 1.5h or 250ms in milliseconds, reading a number without a unit as seconds, and whether
+// it has a unit. Up to three decimals are allowed, a millisecond being the smallest tick, so 1.5ms is an error, as is a
+// time too long to be a lag.
+fn parse_time(input: &str) -> IResult<&str, (TimeMoment, bool), ErrorTree<&str>> {
+    parse_from_str::<_, TimeMoment, _>(digit1)
+        .and(char('.')
+            .precedes(take_while_m_n(1, 3, |character: char| character.is_ascii_digit()))
+            .opt())
+        .and(parse_unit.opt())
+        .map_res_cut(|((whole, decimals), unit)| {
+            let thousandths = decimals.map_or(0, |decimals| decimals.parse::<TimeMoment>().unwrap() * 10usize.pow(3 - decimals.len() as u32));
+            let thousandths_of_milliseconds = whole
+                .checked_mul(1000)
+                .and_then(|whole| whole.checked_add(thousandths))
+                .and_then(|thousandths| thousandths.checked_mul(unit.unwrap_or(MILLISECONDS_PER_SECOND)))
+                .filter(|&thousandths| Lag::try_from(thousandths / 1000).is_ok())
+                .ok_or(TimeError::Overflow)?;
+            if thousandths_of_milliseconds % 1000 != 0 {
+                return Err(TimeError::BelowMillisecond);
+            }
+            Ok((thousandths_of_milliseconds / 1000, unit.is_some()))
+        })
+        .parse(input)
+}
+
+// whether any time is without a unit and whether any time has one
+type Units = (bool, bool);
+
+fn units_of(with_unit: bool) -> Units {
+    (!with_unit, with_unit)
+}
+
+fn join_units((without_unit, with_unit): Units, (other_without_unit, other_with_unit): Units) -> Units {
+    (without_unit || other_without_unit, with_unit || other_with_unit)
+}
+
+// optimistic..likely..pessimistic, e.g. 2..3..8 or 1h..2h..4h, in milliseconds, and which of them have a unit.
+// The optimistic duration should not exceed the likely one, nor the likely duration the pessimistic one.
+fn parse_estimate(input: &str) -> IResult<&str, (Estimate, Units), ErrorTree<&str>> {
+    let (input, (optimistic, optimistic_with_unit)) = parse_time
+        .terminated(tag(".."))
+        .parse(input)?;
+
+    verify(
+        parse_time.terminated(tag("..")).and(parse_time),
+        move |((likely, _), (pessimistic, _))| optimistic <= *likely && likely <= pessimistic,
+    )
+        .map(move |((likely, likely_with_unit), (pessimistic, pessimistic_with_unit))| (
+            Estimate { optimistic, likely, pessimistic },
+            join_units(join_units(units_of(optimistic_with_unit), units_of(likely_with_unit)), units_of(pessimistic_with_unit)),
+        ))
+        .context("estimate")
+        .cut()
+        .parse(input)
+}
+
+fn parse_duration(input: &str) -> IResult<&str, (Duration, Option<Estimate>, Units), ErrorTree<&str>> {
+    alt((
+        parse_estimate.map(|(estimate, units)| (estimate.likely, Some(estimate), units)),
+        parse_time.map(|(duration, with_unit)| (duration, None, units_of(with_unit))),
+    ))
         .terminated(char(')'))
         .context("duration")
         .parse(input)
 }
 
-fn parse_eof(input: &str) -> IResult<&str, Vec<String>, ErrorTree<&str>> {
-    let (input, _) = multispace0
+// from # to the end of the line
+fn parse_comment(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
+    char('#')
+        .precedes(not_line_ending)
+        .context("comment")
+        .parse(input)
+}
+
+// whitespace, including line endings, and comments
+fn parse_blank0(input: &str) -> IResult<&str, (), ErrorTree<&str>> {
+    many0_count(alt((multispace1, parse_comment)))
+        .value(())
+        .parse(input)
+}
+
+fn parse_blank1(input: &str) -> IResult<&str, (), ErrorTree<&str>> {
+    many1_count(alt((multispace1, parse_comment)))
+        .value(())
+        .parse(input)
+}
+
+// the rest of a line without anything but whitespace and a comment
+fn parse_blank_line(input: &str) -> IResult<&str, (), ErrorTree<&str>> {
+    space0
+        .terminated(parse_comment.opt())
+        .terminated(line_ending)
+        .value(())
+        .parse(input)
+}
+
+fn parse_eof(input: &str) -> IResult<&str, Vec<ParsedDependency<'_>>, ErrorTree<&str>> {
+    let (input, _) = parse_blank0
         .terminated(eof)
         .context("end-of-file")
         .parse(input)?;
@@ -46,23 +201,19 @@ fn parse_eof(input: &str) -> IResult<&str, Vec<String>, ErrorTree<&str>> {
     Ok((input, vec![]))
 }
 
-fn parse_check_no_dependencies(input: &str) -> IResult<&str, Vec<String>, ErrorTree<&str>> {
-    let (input, _) = space0
-        .precedes(line_ending)
-        .terminated(alphanumeric1)
-        .complete()
-        .peek()
-        .terminated(space0)
-        .terminated(line_ending)
+// the line of the task ends and, after any blank lines and comments, the next task starts at the beginning of a line
+fn parse_check_no_dependencies(input: &str) -> IResult<&str, Vec<ParsedDependency<'_>>, ErrorTree<&str>> {
+    let (input, _) = parse_blank_line
+        .terminated(many0_count(parse_blank_line).terminated(alphanumeric1.value(()).or(eof.value(()))).complete().peek())
         .context("check-no-dependencies")
         .parse(input)?;
 
     Ok((input, vec![]))
 }
 
-fn parse_dependencies(input: &str) -> IResult<&str, Vec<String>, ErrorTree<&str>> {
+fn parse_dependencies(input: &str) -> IResult<&str, Vec<ParsedDependency<'_>>, ErrorTree<&str>> {
     let (input, _) = tag("after")
-        .delimited_by(multispace1)
+        .delimited_by(parse_blank1)
         .complete()
         .cut()
         .context("dependencies")
@@ -71,20 +222,176 @@ fn parse_dependencies(input: &str) -> IResult<&str, Vec<String>, ErrorTree<&str>
     parse_dependencies_array(input)
 }
 
-fn parse_dependencies_array(input: &str) -> IResult<&str, Vec<String>, ErrorTree<&str>> {
+// a task-name, or the name of a task in an included file prefixed by its namespace: build::compile
+fn parse_qualified_name(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
+    recognize(separated_list1(tag("::"), alphanumeric1))
+        .context("qualified-name")
+        .parse(input)
+}
+
+// A, start(A) or finish(A) for finish-to-start, start-to-start or finish-to-finish, with an optional lag: A+2, start(A)-1,
+// B+30m, with the name of the task it refers to as a slice of the input and whether the lag has a unit
+fn parse_dependency(input: &str) -> IResult<&str, ParsedDependency<'_>, ErrorTree<&str>> {
+    let (input, (kind, name)) = alt((
+        tag("start")
+            .precedes(parse_qualified_name.delimited_by(space0).preceded_by(char('(')).terminated(char(')')))
+            .map(|name| (DependencyKind::StartToStart, name)),
+        tag("finish")
+            .precedes(parse_qualified_name.delimited_by(space0).preceded_by(char('(')).terminated(char(')')))
+            .map(|name| (DependencyKind::FinishToFinish, name)),
+        parse_qualified_name.map(|name| (DependencyKind::FinishToStart, name)),
+    ))
+        .parse(input)?;
+
+    let (input, lag) = alt((char('+'), char('-')))
+        .delimited_by(space0)
+        .and(parse_time.cut())
+        .map(|(sign, (lag, with_unit))| (if sign == '-' { -(lag as Lag) } else { lag as Lag }, with_unit))
+        .opt()
+        .parse(input)?;
+
+    let (lag, units) = lag.map_or((0, (false, false)), |(lag, with_unit)| (lag, units_of(with_unit)));
+    Ok((input, (name, Dependency { name: name.to_string(), id: None, kind, lag, location: None }, units)))
+}
+
+fn parse_dependencies_array(input: &str) -> IResult<&str, Vec<ParsedDependency<'_>>, ErrorTree<&str>> {
     collect_separated_terminated(
-        alphanumeric1.parse_from_str(),
-        char(',').delimited_by(multispace0),
-        char(']').preceded_by(space0),
+        parse_dependency.context("dependency"),
+        char(',').delimited_by(parse_blank0),
+        char(']').preceded_by(parse_blank0),
     )
-        .preceded_by(char('[').terminated(space0).complete())
+        .preceded_by(char('[').terminated(parse_blank0).complete())
         .complete()
         .cut()
         .context("dependencies-array")
         .parse(input)
 }
 
-fn parse_optional_dependencies(input: &str) -> IResult<&str, Vec<String>, ErrorTree<&str>> {
+fn parse_optional_priority(input: &str) -> IResult<&str, Priority, ErrorTree<&str>> {
+    let (input, priority) = tag("priority")
+        .delimited_by(parse_blank1)
+        .complete()
+        .precedes(parse_from_str(digit1).cut())
+        .opt()
+        .context("priority")
+        .parse(input)?;
+
+    Ok((input, priority.unwrap_or(0)))
+}
+
+fn parse_resource(input: &str) -> IResult<&str, (String, ResourceAmount), ErrorTree<&str>> {
+    alphanumeric1
+        .parse_from_str()
+        .terminated(char(':').delimited_by(space0))
+        .and(parse_from_str(digit1))
+        .context("resource")
+        .parse(input)
+}
+
+fn parse_resources_array(input: &str) -> IResult<&str, Vec<(String, ResourceAmount)>, ErrorTree<&str>> {
+    collect_separated_terminated(
+        parse_resource,
+        char(',').delimited_by(parse_blank0),
+        char(']').preceded_by(parse_blank0),
+    )
+        .preceded_by(char('[').terminated(parse_blank0).complete())
+        .complete()
+        .cut()
+        .context("resources-array")
+        .parse(input)
+}
+
+fn parse_optional_resources(input: &str) -> IResult<&str, Vec<(String, ResourceAmount)>, ErrorTree<&str>> {
+    let (input, resources) = tag("uses")
+        .delimited_by(parse_blank1)
+        .complete()
+        .precedes(parse_resources_array)
+        .opt()
+        .context("resources")
+        .parse(input)?;
+
+    Ok((input, resources.unwrap_or_default()))
+}
+
+// the capacity per resource, declared on the first line of the job: capacity [gpu:2, mem:8], with the keyword for its
+// location
+fn parse_capacities(input: &str) -> IResult<&str, ParsedCapacities<'_>, ErrorTree<&str>> {
+    tag("capacity")
+        .terminated(space1)
+        .complete()
+        .and(parse_resources_array)
+        .terminated(parse_blank0)
+        .context("capacities")
+        .parse(input)
+}
+
+// a line of the header with metadata of the job: name, owner or a description, e.g. owner: Build team
+fn parse_metadata_entry(input: &str) -> IResult<&str, (&str, &str), ErrorTree<&str>> {
+    alt((tag("name"), tag("owner"), tag("description")))
+        .terminated(char(':'))
+        .and(take_till1(|character| character == '#' || character == '\r' || character == '\n')
+            .map(str::trim_end)
+            .preceded_by(space0)
+            .cut())
+        .terminated(parse_blank0)
+        .context("metadata")
+        .parse(input)
+}
+
+fn parse_metadata(input: &str) -> IResult<&str, Metadata, ErrorTree<&str>> {
+    let (input, entries) = many0(parse_metadata_entry).parse(input)?;
+
+    let mut metadata = Metadata::default();
+    for (key, value) in entries {
+        let field = match key {
+            "name" => &mut metadata.name,
+            "owner" => &mut metadata.owner,
+            _ => &mut metadata.description,
+        };
+        // a key given twice would leave it unclear which value is meant
+        if field.is_some() {
+            let error = DuplicateMetadataError { key: key.to_string() };
+            return Err(nom::Err::Failure(ErrorTree::from_external_error(key, ErrorKind::Verify, error)));
+        }
+        *field = Some(value.to_string());
+    }
+    Ok((input, metadata))
+}
+
+// include "build.tasks.in", or with a namespace for its tasks: include "build.tasks.in" as build
+fn parse_include(input: &str) -> IResult<&str, (&str, &str, Option<&str>), ErrorTree<&str>> {
+    let (input, keyword) = tag("include")
+        .terminated(space1)
+        .complete()
+        .parse(input)?;
+
+    take_till1(|character| character == '"' || character == '\r' || character == '\n')
+        .delimited_by(char('"'))
+        .and(alphanumeric1.preceded_by(tag("as").delimited_by(space1)).opt())
+        .map(|(path, namespace)| (keyword, path, namespace))
+        .terminated(parse_blank0)
+        .cut()
+        .context("include")
+        .parse(input)
+}
+
+// the shell command that runs the task in exec mode: run "make lib", with \" for a double quote and \\ for a backslash
+// in the command, e.g. run "echo \"done\"", and no other escapes
+fn parse_optional_command(input: &str) -> IResult<&str, Option<String>, ErrorTree<&str>> {
+    let command_text = escaped_transform(none_of("\\\"\r\n"), '\\', alt((value("\\", char('\\')), value("\"", char('"')))))
+        .verify(|command: &String| !command.is_empty());
+    tag("run")
+        .delimited_by(parse_blank1)
+        .complete()
+        .precedes(command_text
+            .delimited_by(char('"'))
+            .cut())
+        .opt()
+        .context("command")
+        .parse(input)
+}
+
+fn parse_optional_dependencies(input: &str) -> IResult<&str, Vec<ParsedDependency<'_>>, ErrorTree<&str>> {
     alt((
         parse_eof,
         parse_check_no_dependencies,
@@ -94,31 +401,224 @@ fn parse_optional_dependencies(input: &str) -> IResult<&str, Vec<String>, ErrorT
         .parse(input)
 }
 
-fn parse_unscheduled_task(input: &str) -> IResult<&str, Task, ErrorTree<&str>> {
+// the task-name and the names in its dependencies as slices of the input, to locate them, and the task with its
+// duration and lags in milliseconds, and which of them have a unit
+fn parse_unscheduled_task(input: &str) -> IResult<&str, ParsedTask<'_>, ErrorTree<&str>> {
     let (input, name) = parse_name(input)?;
-    let (input, duration) = parse_duration(input)?;
+    let (input, (duration, estimate, duration_units)) = parse_duration(input)?;
+    let (input, priority) = parse_optional_priority(input)?;
+    let (input, resources) = parse_optional_resources(input)?;
+    let (input, command) = parse_optional_command(input)?;
     let (input, dependencies) = parse_optional_dependencies(input)?;
 
-    let task = Task::new(name, duration, dependencies);
+    let units = dependencies.iter().fold(duration_units, |units, (_, _, lag_units)| join_units(units, *lag_units));
+    let dependency_names = dependencies.iter().map(|(name, _, _)| *name).collect();
+    let task = Task::new(name, duration, vec![])
+        .after(dependencies.into_iter().map(|(_, dependency, _)| dependency).collect())
+        .with_priority(priority)
+        .using(resources);
+    let task = match estimate {
+        Some(estimate) => task.estimated(estimate),
+        None => task,
+    };
+    let task = match command {
+        Some(command) => task.running(&command),
+        None => task,
+    };
 
-    Ok((input, task))
+    Ok((input, (name, dependency_names, task, units)))
 }
 
-pub fn parse_job(input: &str) -> Result<Vec<Task>, ErrorTree<nom_supreme::final_parser::Location>> {
-    final_parser(
-        collect_separated_terminated(
-            parse_unscheduled_task,
-            multispace0,
-            multispace0.all_consuming(),
-        )
-            .context("parse_job"),
-    )(input)
+// A number without a unit in a file whose other times have one could be meant as seconds or as another unit, so the
+// times in a file all have a unit or none do. The error is at the task-name of the first task where both appear.
+fn check_units<'a>(tasks: &[ParsedTask<'a>]) -> Result<(), nom::Err<ErrorTree<&'a str>>> {
+    let mut units = (false, false);
+    for (name, _, _, task_units) in tasks {
+        units = join_units(units, *task_units);
+        if units == (true, true) {
+            return Err(nom::Err::Failure(ErrorTree::from_external_error(*name, ErrorKind::Verify, TimeError::MixedUnits)));
+        }
+    }
+    Ok(())
+}
+
+// Integer durations and lags keep counting in time units. Otherwise every tick is a thousandth of a time unit,
+// which is a millisecond when the times have a unit.
+fn time_scale(tasks: &[ParsedTask]) -> TimeScale {
+    let all_times = || tasks.iter().flat_map(|(_, _, task, _)| {
+        let estimate = task.estimate.iter().flat_map(|estimate| [estimate.optimistic as Lag, estimate.pessimistic as Lag]);
+        once(task.duration as Lag).chain(estimate).chain(task.dependencies.iter().map(|dependency| dependency.lag))
+    });
+    if tasks.iter().any(|(_, _, _, (_, with_unit))| *with_unit) {
+        TimeScale::Milliseconds
+    } else if all_times().any(|time| time % 1000 != 0) {
+        TimeScale::Thousandths
+    } else {
+        TimeScale::Units
+    }
+}
+
+type ParsedDependency<'a> = (&'a str, Dependency, Units);
+type ParsedTask<'a> = (&'a str, Vec<&'a str>, Task, Units);
+type ParsedCapacities<'a> = (&'a str, Vec<(String, ResourceAmount)>);
+type Header<'a> = (Metadata, Option<ParsedCapacities<'a>>, Vec<(&'a str, &'a str, Option<&'a str>)>);
+type JobFileParts<'a> = (Metadata, Option<ParsedCapacities<'a>>, Vec<(&'a str, &'a str, Option<&'a str>)>, Vec<ParsedTask<'a>>);
+
+// the metadata, the capacity line and the include lines
+fn parse_header(input: &str) -> IResult<&str, Header<'_>, ErrorTree<&str>> {
+    parse_metadata
+        .and(parse_capacities.opt())
+        .and(many0(parse_include))
+        .preceded_by(parse_blank0)
+        .map(|((metadata, capacities), includes)| (metadata, capacities, includes))
+        .parse(input)
+}
+
+// the header and the tasks; a job file that includes other files can leave all tasks to them
+fn parse_job_file(input: &str) -> IResult<&str, JobFileParts<'_>, ErrorTree<&str>> {
+    let (input, (metadata, capacities, includes)) = parse_header(input)?;
+
+    let mut tasks = collect_separated_terminated(
+        parse_unscheduled_task,
+        parse_blank0,
+        parse_blank0.all_consuming(),
+    );
+    let (input, tasks): (_, Vec<ParsedTask>) = if includes.is_empty() {
+        tasks.parse(input)?
+    } else {
+        tasks.opt().map(Option::unwrap_or_default).terminated(parse_blank0).parse(input)?
+    };
+    check_units(&tasks)?;
+
+    Ok((input, (metadata, capacities, includes, tasks)))
+}
+
+// The location of a slice of the input from its offset. The offsets at which the lines start are found once, so
+// locating every name in a large file takes a binary search each instead of a scan of the input up to the name.
+fn build_locator(input: &str) -> impl Fn(&str) -> SourceLocation + '_ {
+    let line_starts: Vec<usize> = once(0).chain(input.match_indices('\n').map(|(offset, _)| offset + 1)).collect();
+    move |slice: &str| {
+        let offset = slice.as_ptr() as usize - input.as_ptr() as usize;
+        let line = line_starts.partition_point(|&start| start <= offset);
+        SourceLocation::new(line, offset - line_starts[line - 1] + 1)
+    }
+}
+
+fn build_job<'a>(input: &'a str, (metadata, capacities, includes, tasks): JobFileParts<'a>) -> Job {
+    // task-names, the names in dependencies and the capacity and include keywords are slices of the input, so their
+    // offset gives their location
+    let locate = build_locator(input);
+
+    let time_scale = time_scale(&tasks);
+    let tasks = tasks
+        .into_iter()
+        .map(|(name, dependency_names, mut task, _)| {
+            task.location = Some(locate(name));
+            for (dependency, name) in task.dependencies.iter_mut().zip(dependency_names) {
+                dependency.location = Some(locate(name));
+            }
+            if time_scale == TimeScale::Units {
+                task.rescale(1, 1000);
+            }
+            task
+        })
+        .collect();
+
+    let includes = includes
+        .into_iter()
+        .map(|(keyword, path, namespace)| Include {
+            path: path.to_string(),
+            namespace: namespace.map(str::to_string),
+            location: locate(keyword),
+        })
+        .collect();
+
+    let (capacity_location, capacities) = match capacities {
+        Some((keyword, capacities)) => (Some(locate(keyword)), capacities),
+        None => (None, vec![]),
+    };
+
+    Job {
+        metadata,
+        capacities,
+        capacity_location,
+        includes,
+        tasks,
+        time_scale,
+    }
+}
+
+pub fn parse_job(input: &str) -> Result<Job, ErrorTree<Location>> {
+    let parts = final_parser(parse_job_file.context("parse_job"))(input)?;
+
+    Ok(build_job(input, parts))
+}
+
+// the first line from the start of the input on that starts with a task-name and its duration, so a line continuing
+// the dependencies of a task, like start(A)+1, is not taken for a task
+fn skip_to_task(input: &str) -> &str {
+    let mut rest = input;
+    while !rest.is_empty() && parse_name.and(parse_duration).parse(rest).is_err() {
+        rest = rest.find('\n').map_or(&rest[rest.len()..], |end_of_line| &rest[end_of_line + 1..]);
+    }
+    rest
+}
+
+// The job with every task that parses, and every syntax error. After an error parsing continues at the next line that
+// starts with a task-name, so all errors in the file are found in one run. A task that does not parse is kept by its
+// name only, without duration or dependencies, so the job can still be checked.
+pub fn parse_job_with_recovery(input: &str) -> (Job, Vec<ErrorTree<Location>>) {
+    let first_error = match parse_job(input) {
+        Ok(job) => return (job, vec![]),
+        Err(error) => error,
+    };
+
+    let mut errors = vec![];
+    let mut record = |error: nom::Err<ErrorTree<&str>>| {
+        if let nom::Err::Error(error) | nom::Err::Failure(error) = error {
+            errors.push(error.extract_context(input));
+        }
+    };
+
+    let (mut rest, (metadata, capacities, includes)) = parse_header(input).unwrap_or_else(|error| {
+        record(error);
+        (skip_to_task(input), Default::default())
+    });
+    let mut tasks = vec![];
+    while let Ok((next, _)) = parse_blank0(rest) {
+        if next.is_empty() {
+            break;
+        }
+        match parse_unscheduled_task(next) {
+            Ok((next, task)) => {
+                tasks.push(task);
+                rest = next;
+            }
+            Err(error) => {
+                record(error);
+                // the task is kept by its name, so no task depending on it is reported to depend on an unknown task
+                if let Ok((_, name)) = parse_name(next) {
+                    tasks.push((name, vec![], Task::new(name, 0, vec![]), (false, false)));
+                }
+                rest = skip_to_task(next.find('\n').map_or("", |end_of_line| &next[end_of_line + 1..]));
+            }
+        }
+    }
+
+    if let Err(error) = check_units(&tasks) {
+        record(error);
+    }
+    if errors.is_empty() {
+        errors.push(first_error);
+    }
+    (build_job(input, (metadata, capacities, includes, tasks)), errors)
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use nom_supreme::error::BaseErrorKind;
 
     #[test]
     fn test_parse_name() {
@@ -127,7 +627,28 @@ mod tests {
 
     #[test]
     fn test_parse_duration() {
-        assert_eq!(parse_duration(r#"1) after"#).unwrap(), (" after", 1));
+        assert_eq!(parse_duration(r#"1) after"#).unwrap(), (" after", (1000, None, (true, false))));
+        assert_eq!(parse_duration(r#"1.25)"#).unwrap(), ("", (1250, None, (true, false))));
+        assert_eq!(parse_duration(r#"1.5h)"#).unwrap(), ("", (5_400_000, None, (false, true))));
+        assert_eq!(parse_duration(r#"90s)"#).unwrap(), ("", (90_000, None, (false, true))));
+        assert_eq!(parse_duration(r#"2min)"#).unwrap(), ("", (120_000, None, (false, true))));
+        assert_eq!(parse_duration(r#"250ms)"#).unwrap(), ("", (250, None, (false, true))));
+        assert!(parse_duration(r#"1.)"#).is_err());
+        assert!(parse_duration(r#"1.2345)"#).is_err());
+        assert!(parse_duration(r#"3w)"#).is_err());
+        assert_eq!(parse_duration(r#"0.001s)"#).unwrap(), ("", (1, None, (false, true))));
+    }
+
+    #[test]
+    fn test_parse_time_beyond_milliseconds() {
+        let message = |input| match parse_time(input) {
+            Err(nom::Err::Failure(ErrorTree::Base { kind: BaseErrorKind::External(error), .. })) => error.to_string(),
+            other => panic!("unexpected result {:?}", other),
+        };
+        assert_eq!(message("1.5ms"), "time is more precise than a millisecond");
+        assert_eq!(message("99999999999999999d"), "time is too long");
+        assert!(parse_job("A(1.5ms)").is_err());
+        assert!(parse_job("A(1)\nB(1) after [A+0.1ms]").is_err());
     }
 
     #[test]
@@ -141,7 +662,7 @@ C(1)"#).unwrap(), ("C(1)", vec![]));
     fn test_parse_dependencies_and_parse_dependencies_array() {
         assert_eq!(parse_dependencies(r#" after [D
         ,
-        E  ,   F   ] "#).unwrap(), (" ", vec!["D".to_string(), "E".to_string(), "F".to_string()]));
+        E  ,   F   ] "#).unwrap(), (" ", vec![("D", Dependency::finish_to_start("D"), (false, false)), ("E", Dependency::finish_to_start("E"), (false, false)), ("F", Dependency::finish_to_start("F"), (false, false))]));
         assert!(parse_dependencies(r#" after [D
         ,
         E     F   ] "#).is_err());
@@ -150,18 +671,286 @@ C(1)"#).unwrap(), ("C(1)", vec![]));
         E   ,  F    "#).is_err());
     }
 
+    #[test]
+    fn test_parse_optional_resources() {
+        assert_eq!(parse_optional_resources(r#" uses [gpu:1,
+          mem : 4] after [B]"#).unwrap(), (" after [B]", vec![("gpu".to_string(), 1), ("mem".to_string(), 4)]));
+        assert_eq!(parse_optional_resources(r#" after [B]"#).unwrap(), (" after [B]", vec![]));
+        assert!(parse_optional_resources(r#" uses [gpu]"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_dependency_types() {
+        assert_eq!(parse_dependencies(r#" after [A+2, start(B), finish( C )-1, D - 3]"#).unwrap(), ("", vec![
+            ("A", Dependency { name: "A".to_string(), kind: DependencyKind::FinishToStart, lag: 2000, ..Default::default() }, (true, false)),
+            ("B", Dependency { name: "B".to_string(), kind: DependencyKind::StartToStart, lag: 0, ..Default::default() }, (false, false)),
+            ("C", Dependency { name: "C".to_string(), kind: DependencyKind::FinishToFinish, lag: -1000, ..Default::default() }, (true, false)),
+            ("D", Dependency { name: "D".to_string(), kind: DependencyKind::FinishToStart, lag: -3000, ..Default::default() }, (true, false)),
+        ]));
+        assert_eq!(parse_dependencies(r#" after [starter, B+30m]"#).unwrap(), ("", vec![
+            ("starter", Dependency::finish_to_start("starter"), (false, false)),
+            ("B", Dependency { name: "B".to_string(), kind: DependencyKind::FinishToStart, lag: 1_800_000, ..Default::default() }, (false, true)),
+        ]));
+        assert!(parse_dependencies(r#" after [A+]"#).is_err());
+        assert!(parse_dependencies(r#" after [start(B]"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_optional_priority() {
+        assert_eq!(parse_optional_priority(r#" priority 3 after [B]"#).unwrap(), (" after [B]", 3));
+        assert_eq!(parse_optional_priority(r#" after [B]"#).unwrap(), (" after [B]", 0));
+        assert!(parse_optional_priority(r#" priority high"#).is_err());
+    }
+
+    #[test]
+    fn locate_slices_of_the_input() {
+        let input = "A(1)\n\nB(2) after [A]";
+        let locate = build_locator(input);
+        for offset in [0, 3, 4, 5, 6, 17] {
+            let Location { line, column } = Location::locate_tail(input, &input[offset..]);
+            assert_eq!(locate(&input[offset..]), SourceLocation::new(line, column));
+        }
+    }
+
+    #[test]
+    fn test_parse_job_with_commands() {
+        assert_eq!(parse_job(r#"lib(1) run "make lib"
+test(2) uses [cpu:2] run "cargo test --all" after [lib]
+docs(1) after [lib]"#).unwrap().tasks, vec![Task::new("lib", 1, vec![]).running("make lib").at(1, 1),
+                           Task::new("test", 2, vec!["lib".to_string()]).using(vec![("cpu".to_string(), 2)]).running("cargo test --all").at(2, 1),
+                           Task::new("docs", 1, vec!["lib".to_string()]).at(3, 1)]);
+        assert!(parse_job(r#"A(1) run "make lib
+B(1)"#).is_err());
+        assert!(parse_job(r#"A(1) run """#).is_err());
+    }
+
+    #[test]
+    fn test_parse_optional_command_with_escapes() {
+        assert_eq!(parse_optional_command(r#" run "echo \"a\\b\"" after [B]"#).unwrap(), (" after [B]", Some(r#"echo "a\b""#.to_string())));
+        assert_eq!(parse_optional_command(r#" after [B]"#).unwrap(), (" after [B]", None));
+        assert!(matches!(parse_optional_command(r#" run "echo \n""#), Err(nom::Err::Failure(_))));
+        assert!(matches!(parse_optional_command(r#" run "echo \"a"#), Err(nom::Err::Failure(_))));
+    }
+
+    #[test]
+    fn test_parse_job_with_priorities() {
+        assert_eq!(parse_job(r#"A(3) priority 2 uses [gpu:1]
+B(1) priority 1 after [A]
+C(1)"#).unwrap().tasks, vec![Task::new("A", 3, vec![]).with_priority(2).using(vec![("gpu".to_string(), 1)]).at(1, 1),
+                           Task::new("B", 1, vec!["A".to_string()]).with_priority(1).at(2, 1),
+                           Task::new("C", 1, vec![]).at(3, 1)]);
+    }
+
+    #[test]
+    fn test_parse_job_with_resources() {
+        assert_eq!(parse_job(r#"capacity [gpu:2, mem:8]
+A(3) uses [gpu:1, mem:4]
+B(1) uses [gpu:2] after [A]
+C(1) after [A]"#).unwrap(), Job {
+            metadata: Metadata::default(),
+            includes: vec![],
+            capacities: vec![("gpu".to_string(), 2), ("mem".to_string(), 8)],
+            capacity_location: Some(SourceLocation::new(1, 1)),
+            tasks: vec![Task::new("A", 3, vec![]).using(vec![("gpu".to_string(), 1), ("mem".to_string(), 4)]).at(2, 1),
+                        Task::new("B", 1, vec!["A".to_string()]).using(vec![("gpu".to_string(), 2)]).at(3, 1),
+                        Task::new("C", 1, vec!["A".to_string()]).at(4, 1)],
+            time_scale: TimeScale::Units,
+        });
+        assert!(parse_job(r#"capacity [gpu 2]
+A(1)"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_job_with_fractional_durations() {
+        let job = parse_job(r#"A(1.5)
+B(2) after [A+0.25]"#).unwrap();
+        assert_eq!(job.time_scale, TimeScale::Thousandths);
+        assert_eq!(job.tasks, vec![Task::new("A", 1500, vec![]).at(1, 1),
+                                   Task::new("B", 2000, vec![]).after(vec![Dependency { name: "A".to_string(), kind: DependencyKind::FinishToStart, lag: 250, ..Default::default() }]).at(2, 1)]);
+    }
+
+    #[test]
+    fn test_parse_job_with_units() {
+        let job = parse_job(r#"A(1h)
+B(30s) after [start(A)+15m]"#).unwrap();
+        assert_eq!(job.time_scale, TimeScale::Milliseconds);
+        assert!(parse_job("A(1)\nB(500ms) after [A]").is_err());
+        assert!(parse_job("A(1h)\nB(1) after [A]").is_err());
+        assert!(parse_job("A(1h)\nB(1s) after [A+2]").is_err());
+        assert!(parse_job("A(1..2h..3h)").is_err());
+        assert_eq!(job.tasks, vec![Task::new("A", 3_600_000, vec![]).at(1, 1),
+                                   Task::new("B", 30_000, vec![]).after(vec![Dependency { name: "A".to_string(), kind: DependencyKind::StartToStart, lag: 900_000, ..Default::default() }]).at(2, 1)]);
+    }
+
+    #[test]
+    fn test_parse_estimate() {
+        assert_eq!(parse_duration(r#"2..3..8)"#).unwrap(), ("", (3000, Some(Estimate { optimistic: 2000, likely: 3000, pessimistic: 8000 }), (true, false))));
+        assert_eq!(parse_duration(r#"30m..1h..2h)"#).unwrap().1.2, (false, true));
+        assert_eq!(parse_duration(r#"1..1h..2h)"#).unwrap().1.2, (true, true));
+        assert!(parse_duration(r#"2..3)"#).is_err());
+        assert!(parse_duration(r#"3..2..8)"#).is_err());
+        assert!(parse_duration(r#"2..8..3)"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_job_with_estimates() {
+        let job = parse_job(r#"A(2..3..8)
+B(1) after [A]"#).unwrap();
+        assert_eq!(job.time_scale, TimeScale::Units);
+        assert_eq!(job.tasks, vec![Task::new("A", 0, vec![]).estimated(Estimate { optimistic: 2, likely: 3, pessimistic: 8 }).at(1, 1),
+                                   Task::new("B", 1, vec!["A".to_string()]).at(2, 1)]);
+    }
+
+    #[test]
+    fn test_parse_comment_and_blank_lines() {
+        assert_eq!(parse_blank0(" # first\n\n  # second\nA(1)").unwrap(), ("A(1)", ()));
+        assert_eq!(parse_check_no_dependencies(" # note\n\n# about B\nB(1)").unwrap(), ("\n# about B\nB(1)", vec![]));
+        assert!(parse_check_no_dependencies("\n\n  after [A]").is_err());
+    }
+
+    #[test]
+    fn test_parse_job_with_comments_and_blank_lines() {
+        assert_eq!(parse_job(r#"# a job with comments
+A(1) # without dependencies
+
+B(1) after [A] # after A
+C(1)
+  # C waits for A and B
+  after [A, # first A
+         B]
+
+D(1)
+"#).unwrap().tasks, vec![Task::new("A", 1, vec![]).at(2, 1),
+                   Task::new("B", 1, vec!["A".to_string()]).at(4, 1),
+                   Task::new("C", 1, vec!["A".to_string(), "B".to_string()]).at(5, 1),
+                   Task::new("D", 1, vec![]).at(10, 1)]);
+    }
+
+    #[test]
+    fn test_parse_job_with_metadata() {
+        let job = parse_job(r#"name: Release 1.2  # the next release
+owner: Build team
+description: Every night
+capacity [seat:1]
+
+A(1) uses [seat:1]"#).unwrap();
+        assert_eq!(job.metadata, Metadata {
+            name: Some("Release 1.2".to_string()),
+            owner: Some("Build team".to_string()),
+            description: Some("Every night".to_string()),
+        });
+        assert_eq!(job.capacities, vec![("seat".to_string(), 1)]);
+        assert_eq!(job.tasks, vec![Task::new("A", 1, vec![]).using(vec![("seat".to_string(), 1)]).at(6, 1)]);
+        assert!(parse_job(r#"name:
+A(1)"#).is_err());
+        assert!(parse_job(r#"unit: days
+A(1)"#).is_err());
+        match parse_job(r#"name: Release
+owner: Build team
+name: Nightly
+A(1)"#) {
+            Err(ErrorTree::Stack { base, .. }) => match *base {
+                ErrorTree::Base { location, kind: BaseErrorKind::External(error) } => {
+                    assert_eq!(location, Location { line: 3, column: 1 });
+                    assert_eq!(error.to_string(), "metadata name is given more than once");
+                }
+                other => panic!("unexpected error {:?}", other),
+            },
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_job_failure_location_after_comments() {
+        let error = parse_job(r#"# comment
+A(1) # comment
+B(1) afer [A]"#).unwrap_err();
+        match error {
+            ErrorTree::Stack { base, .. } => match *base {
+                ErrorTree::Base { location, .. } => assert_eq!(location, Location { line: 3, column: 6 }),
+                other => panic!("unexpected error {:?}", other),
+            },
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_job_with_includes() {
+        let job = parse_job(r#"name: Release
+include "build.tasks.in" as build
+include "shared/docs.tasks.in"
+
+release(1) after [build::compile, start(build::test)]"#).unwrap();
+        assert_eq!(job.includes, vec![
+            Include { path: "build.tasks.in".to_string(), namespace: Some("build".to_string()), location: SourceLocation::new(2, 1) },
+            Include { path: "shared/docs.tasks.in".to_string(), namespace: None, location: SourceLocation::new(3, 1) },
+        ]);
+        assert_eq!(job.tasks, vec![Task::new("release", 1, vec![]).after(vec![
+            Dependency::finish_to_start("build::compile"),
+            Dependency { name: "build::test".to_string(), kind: DependencyKind::StartToStart, lag: 0, ..Default::default() },
+        ]).at(5, 1)]);
+        let locations: Vec<Option<SourceLocation>> = job.tasks[0].dependencies
+            .iter()
+            .map(|dependency| dependency.location.clone())
+            .collect();
+        assert_eq!(locations, vec![Some(SourceLocation::new(5, 19)), Some(SourceLocation::new(5, 41))]);
+        assert_eq!(parse_job(r#"include "build.tasks.in""#).unwrap().tasks, vec![]);
+        assert!(parse_job(r#"include build.tasks.in
+A(1)"#).is_err());
+        assert!(parse_job("").is_err());
+    }
+
+    #[test]
+    fn test_parse_job_with_recovery() {
+        let (job, errors) = parse_job_with_recovery(r#"A(1)
+B(1) afer [A]
+C(2) after [A
+D(1) after [A]
+E(x)
+F(1) after [D]
+"#);
+        let locations: Vec<(usize, usize)> = errors
+            .iter()
+            .map(|error| match error {
+                ErrorTree::Stack { base, .. } => match base.as_ref() {
+                    ErrorTree::Base { location, .. } => (location.line, location.column),
+                    ErrorTree::Alt(alternatives) => match &alternatives[0] {
+                        ErrorTree::Base { location, .. } => (location.line, location.column),
+                        _ => (0, 0),
+                    },
+                    _ => (0, 0),
+                },
+                _ => (0, 0),
+            })
+            .collect();
+        assert_eq!(locations, vec![(2, 6), (4, 1), (5, 3)]);
+        let names: Vec<&str> = job.tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, vec!["A", "B", "C", "D", "E", "F"]);
+        assert_eq!(job.tasks[1], Task::new("B", 0, vec![]).at(2, 1));
+
+        let (job, errors) = parse_job_with_recovery("A(1)\nB(1) after [A]\n");
+        assert_eq!((job.tasks.len(), errors.len()), (2, 0));
+        assert_eq!(parse_job_with_recovery("").1.len(), 1);
+        assert_eq!(parse_job_with_recovery("capacity [gpu 2]\nA(1)\n").0.tasks.len(), 1);
+
+        let (job, errors) = parse_job_with_recovery("A(1)\nB(1) after [A,,\nstart(A)+1,\nfinish(A)]\nC(1) after [B]\n");
+        assert_eq!(errors.len(), 1);
+        let names: Vec<&str> = job.tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, vec!["A", "B", "C"]);
+    }
+
     #[test]
     fn test_parse_job_with_one_task() {
         assert_eq!(parse_job(r#"A(1)
-"#).unwrap(), vec![Task::new("A", 1, vec![])]);
+"#).unwrap().tasks, vec![Task::new("A", 1, vec![]).at(1, 1)]);
     }
 
     #[test]
     fn test_parse_job_with_two_tasks_without_dependencies() {
         assert_eq!(parse_job(r#"A(1)
 B(1)
-"#).unwrap(), vec![Task::new("A", 1, vec![]),
-                   Task::new("B", 1, vec![])]);
+"#).unwrap().tasks, vec![Task::new("A", 1, vec![]).at(1, 1),
+                   Task::new("B", 1, vec![]).at(2, 1)]);
     }
 
     #[test]
@@ -177,14 +966,14 @@ F(1) after
 G(1) after [C]
 H(1) after [D, F]
 I(1) after
-  [F, G]"#).unwrap(), vec![Task::new("A", 1, vec![]),
-                           Task::new("B", 1, vec!["A".to_string()]),
-                           Task::new("C", 1, vec!["A".to_string()]),
-                           Task::new("D", 1, vec!["B".to_string()]),
-                           Task::new("F", 1, vec!["B".to_string(), "C".to_string()]),
-                           Task::new("G", 1, vec!["C".to_string()]),
-                           Task::new("H", 1, vec!["D".to_string(), "F".to_string()]),
-                           Task::new("I", 1, vec!["F".to_string(), "G".to_string()])]);
+  [F, G]"#).unwrap().tasks, vec![Task::new("A", 1, vec![]).at(1, 1),
+                           Task::new("B", 1, vec!["A".to_string()]).at(2, 1),
+                           Task::new("C", 1, vec!["A".to_string()]).at(3, 1),
+                           Task::new("D", 1, vec!["B".to_string()]).at(5, 1),
+                           Task::new("F", 1, vec!["B".to_string(), "C".to_string()]).at(6, 1),
+                           Task::new("G", 1, vec!["C".to_string()]).at(9, 1),
+                           Task::new("H", 1, vec!["D".to_string(), "F".to_string()]).at(10, 1),
+                           Task::new("I", 1, vec!["F".to_string(), "G".to_string()]).at(11, 1)]);
     }
 
     #[test]
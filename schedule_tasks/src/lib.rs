@@ -0,0 +1,21 @@
+pub mod diff;
+pub mod dot;
+pub mod error;
+pub mod executor;
+pub mod gantt;
+pub mod graph;
+pub mod incremental;
+pub mod input;
+pub mod machines;
+pub mod output;
+pub mod policy;
+pub mod resources;
+pub mod scheduler;
+pub mod simulation;
+pub mod state;
+pub mod task;
+pub mod time;
+
+pub use input::parsers::{parse_job, parse_job_with_recovery};
+pub use scheduler::{check_job, schedule_job};
+pub use task::{Job, Schedule, ScheduledTask, Task, TaskId};
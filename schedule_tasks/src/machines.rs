@@ -0,0 +1,378 @@
+use std::cmp::Reverse;
+use std::collections::{
+    BTreeSet,
+    BinaryHeap,
+};
+use crate::error::ScheduleError;
+use crate::graph::{
+    build_graph,
+    resolve_dependencies,
+};
+use crate::output::{
+    build_metadata_output,
+    to_json,
+    Format,
+    MachineOutput,
+    MachineScheduleOutput,
+    MachineTaskOutput,
+};
+use crate::policy::{
+    order_tasks,
+    Policy,
+};
+use crate::resources::{
+    build_resource_pool,
+    ResourcePool,
+};
+use crate::task::{
+    Job,
+    Metadata,
+    Task,
+    TimeMoment,
+};
+use crate::time::TimeScale;
+
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Machine {
+    pub tasks: Vec<Task>,
+    pub available_at: TimeMoment,
+    pub busy_time: TimeMoment,
+    pub idle_time: TimeMoment,
+}
+
+#[derive(Debug, Clone)]
+pub struct MachineScheduler {
+    pub unscheduled_tasks: Vec<Task>,
+    pub machines: Vec<Machine>,
+    pub makespan: TimeMoment,
+    pub resources: ResourcePool,
+    pub policy: Policy,
+    pub time_scale: TimeScale,
+    pub metadata: Metadata,
+}
+
+impl MachineScheduler {
+    pub fn run(self) -> Result<String, ScheduleError> {
+        self.run_with_format(Format::Text)
+    }
+
+    pub fn run_with_format(mut self, format: Format) -> Result<String, ScheduleError> {
+        self.list_schedule()?;
+        self.calculate_idle_times();
+        match format {
+            Format::Text => Ok(self.print_output()),
+            Format::Json => Ok(self.print_json_output()),
+        }
+    }
+
+    // Graham's list scheduling: whenever a machine is free, it takes the first ready task in the order of
+    // the policy of which the resources fit. A task is ready once all its dependencies have started, as
+    // their start- and end-times then tell when it may start.
+    fn list_schedule(&mut self) -> Result<(), ScheduleError> {
+        let graph = build_graph(&self.unscheduled_tasks)?;
+        resolve_dependencies(&mut self.unscheduled_tasks, &graph.names);
+        self.resources.check_capacities(&self.unscheduled_tasks)?;
+        let order = order_tasks(&self.unscheduled_tasks, &graph, self.policy);
+        let mut rank: Vec<usize> = vec![0; order.len()];
+        for (position, &index) in order.iter().enumerate() {
+            rank[index] = position;
+        }
+        let mut number_of_unstarted_dependencies = graph.number_of_dependencies();
+
+        // the tasks that may start now by rank, so iterating them follows the order of the policy, and the tasks
+        // whose dependencies have started but that may only start later, by that time
+        let mut ready: BTreeSet<usize> = (0..order.len()).filter(|&index| number_of_unstarted_dependencies[index] == 0).map(|index| rank[index]).collect();
+        let mut waiting: BinaryHeap<Reverse<(TimeMoment, usize)>> = BinaryHeap::new();
+        let mut running: BinaryHeap<Reverse<(TimeMoment, usize)>> = BinaryHeap::new();
+        let mut start_times: Vec<TimeMoment> = vec![0; self.unscheduled_tasks.len()];
+        let mut end_times: Vec<TimeMoment> = vec![0; self.unscheduled_tasks.len()];
+        let mut number_of_scheduled_tasks = 0;
+        let mut now: TimeMoment = 0;
+
+        while number_of_scheduled_tasks < self.unscheduled_tasks.len() {
+            while let Some(&Reverse((end_time, finished))) = running.peek() {
+                if end_time > now {
+                    break;
+                }
+                running.pop();
+                self.resources.release(&self.unscheduled_tasks[finished]);
+            }
+            while let Some(&Reverse((ready_time, index))) = waiting.peek() {
+                if ready_time > now {
+                    break;
+                }
+                waiting.pop();
+                ready.insert(rank[index]);
+            }
+
+            let mut started = false;
+            // a dependent that becomes ready behind the current task is still taken in this pass
+            let mut next_rank = ready.iter().next().copied();
+            while let Some(task_rank) = next_rank {
+                next_rank = ready.range(task_rank + 1..).next().copied();
+                let index = order[task_rank];
+                let machine = match self.machines.iter().position(|machine| machine.available_at <= now) {
+                    Some(machine) => machine,
+                    None => break,
+                };
+                if !self.resources.fits(&self.unscheduled_tasks[index]) {
+                    continue;
+                }
+                ready.remove(&task_rank);
+
+                let mut task = self.unscheduled_tasks[index].clone();
+                self.resources.acquire(&task);
+                task.start_time = now;
+                task.end_time = now + task.duration;
+                start_times[index] = task.start_time;
+                end_times[index] = task.end_time;
+                self.makespan = self.makespan.max(task.end_time);
+                running.push(Reverse((task.end_time, index)));
+
+                self.machines[machine].available_at = task.end_time;
+                self.machines[machine].busy_time += task.duration;
+                self.machines[machine].tasks.push(task);
+                number_of_scheduled_tasks += 1;
+                started = true;
+
+                for dependent in graph.dependents[index].iter().map(|id| id.index()) {
+                    number_of_unstarted_dependencies[dependent] -= 1;
+                    if number_of_unstarted_dependencies[dependent] == 0 {
+                        let ready_time = self.unscheduled_tasks[dependent].earliest_start(&start_times, &end_times);
+                        if ready_time <= now {
+                            ready.insert(rank[dependent]);
+                            if rank[dependent] > task_rank && next_rank.is_none_or(|next| rank[dependent] < next) {
+                                next_rank = Some(rank[dependent]);
+                            }
+                        } else {
+                            waiting.push(Reverse((ready_time, dependent)));
+                        }
+                    }
+                }
+            }
+            // tasks that just became ready, or that end right away, can start at this same moment
+            if started {
+                continue;
+            }
+
+            // the next moment something changes: a running task ends or a ready task may start
+            let next_end = running.peek().map(|&Reverse((end_time, _))| end_time);
+            let next_ready = waiting.peek().map(|&Reverse((ready_time, _))| ready_time);
+            now = match (next_end, next_ready) {
+                (Some(end_time), Some(ready_time)) => end_time.min(ready_time),
+                (Some(time), None) | (None, Some(time)) => time,
+                (None, None) => break,
+            };
+        }
+
+        Ok(())
+    }
+
+    fn calculate_idle_times(&mut self) {
+        for machine in self.machines.iter_mut() {
+            machine.idle_time = self.makespan - machine.busy_time;
+        }
+    }
+
+    pub fn print_output(&self) -> String {
+        let machines: Vec<String> = self.machines
+            .iter()
+            .enumerate()
+            .map(|(number, machine)| format!("Machine {}: {} (idle: {})", number + 1, self.print_machine_tasks(machine), self.time_scale.format(machine.idle_time)))
+            .collect();
+
+        let output = format!(r#"Minimum: {}
+{}"#, self.time_scale.format(self.makespan), machines.join("\n"));
+        let metadata = self.metadata.print();
+        let output = if metadata.is_empty() { output } else { format!("{}\n{}", metadata, output) };
+
+        let peak_usage = self.resources.print_peak_usage();
+        if peak_usage.is_empty() { output } else { format!("{}\nResources: {}", output, peak_usage) }
+    }
+
+    pub fn print_json_output(&self) -> String {
+        to_json(&MachineScheduleOutput {
+            metadata: build_metadata_output(&self.metadata),
+            makespan: self.makespan,
+            tick: self.time_scale.tick(),
+            machines: self.machines
+                .iter()
+                .enumerate()
+                .map(|(number, machine)| MachineOutput {
+                    machine: number + 1,
+                    tasks: machine.tasks
+                        .iter()
+                        .map(|task| MachineTaskOutput {
+                            name: &task.name,
+                            start_time: task.start_time,
+                            end_time: task.end_time,
+                        })
+                        .collect(),
+                    idle_time: machine.idle_time,
+                })
+                .collect(),
+            resources: self.resources.to_output(),
+        })
+    }
+
+    fn print_machine_tasks(&self, machine: &Machine) -> String {
+        machine.tasks
+            .iter()
+            .map(|task| format!("{}[{}-{}]", task.name, self.time_scale.format(task.start_time), self.time_scale.format(task.end_time)))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+pub fn build_machine_scheduler(unscheduled_tasks: Vec<Task>, number_of_machines: usize) -> MachineScheduler {
+    MachineScheduler {
+        unscheduled_tasks,
+        machines: vec![Default::default(); number_of_machines],
+        makespan: 0,
+        resources: Default::default(),
+        policy: Default::default(),
+        time_scale: Default::default(),
+        metadata: Default::default(),
+    }
+}
+
+// like build_job_scheduler, for a fixed number of machines
+pub fn build_job_machine_scheduler(job: Job, number_of_machines: usize) -> MachineScheduler {
+    let mut scheduler = build_machine_scheduler(job.tasks, number_of_machines);
+    scheduler.resources = build_resource_pool(&job.capacities);
+    scheduler.time_scale = job.time_scale;
+    scheduler.metadata = job.metadata;
+    scheduler
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{
+        Dependency,
+        DependencyKind,
+    };
+
+    fn example_tasks() -> Vec<Task> {
+        vec![
+            Task::new("A", 1, vec![]),
+            Task::new("B", 1, vec!["A".to_string()]),
+            Task::new("C", 1, vec!["A".to_string()]),
+            Task::new("D", 1, vec!["B".to_string()]),
+            Task::new("F", 1, vec!["B".to_string(), "C".to_string()]),
+            Task::new("G", 1, vec!["C".to_string()]),
+            Task::new("H", 1, vec!["D".to_string(), "F".to_string()]),
+            Task::new("I", 1, vec!["F".to_string(), "G".to_string()]),
+        ]
+    }
+
+    #[test]
+    fn run_with_enough_machines() {
+        let scheduler = build_machine_scheduler(example_tasks(), 3);
+
+        assert_eq!(scheduler.run().unwrap(), r#"Minimum: 4
+Machine 1: A[0-1] B[1-2] D[2-3] H[3-4] (idle: 0)
+Machine 2: C[1-2] F[2-3] I[3-4] (idle: 1)
+Machine 3: G[2-3] (idle: 3)"#)
+    }
+
+    #[test]
+    fn run_with_one_machine() {
+        let scheduler = build_machine_scheduler(example_tasks(), 1);
+
+        assert_eq!(scheduler.run().unwrap(), r#"Minimum: 8
+Machine 1: A[0-1] B[1-2] C[2-3] D[3-4] F[4-5] G[5-6] H[6-7] I[7-8] (idle: 0)"#)
+    }
+
+    #[test]
+    fn run_within_resource_capacities() {
+        let a = Task::new("A", 2, vec![]).using(vec![("seat".to_string(), 1)]);
+        let b = Task::new("B", 1, vec![]).using(vec![("seat".to_string(), 1)]);
+        let c = Task::new("C", 1, vec![]);
+
+        let mut scheduler = build_machine_scheduler(vec![a, b, c], 2);
+        scheduler.resources = build_resource_pool(&[("seat".to_string(), 1)]);
+
+        assert_eq!(scheduler.run().unwrap(), r#"Minimum: 3
+Machine 1: A[0-2] B[2-3] (idle: 0)
+Machine 2: C[0-1] (idle: 2)
+Resources: seat 1 of 1"#)
+    }
+
+    #[test]
+    fn run_with_start_to_start_dependency() {
+        let a = Task::new("A", 3, vec![]);
+        let b = Task::new("B", 2, vec![]).after(vec![Dependency { name: "A".to_string(), kind: DependencyKind::StartToStart, lag: 1, ..Default::default() }]);
+        let c = Task::new("C", 1, vec!["B".to_string()]);
+
+        let scheduler = build_machine_scheduler(vec![a, b, c], 2);
+
+        assert_eq!(scheduler.run().unwrap(), r#"Minimum: 4
+Machine 1: A[0-3] C[3-4] (idle: 0)
+Machine 2: B[1-3] (idle: 2)"#)
+    }
+
+    #[test]
+    fn run_with_longest_processing_time_first() {
+        let a = Task::new("A", 1, vec![]);
+        let b = Task::new("B", 1, vec![]);
+        let c = Task::new("C", 2, vec![]);
+
+        let mut scheduler = build_machine_scheduler(vec![a, b, c], 2);
+        scheduler.policy = Policy::LongestProcessingTime;
+
+        assert_eq!(scheduler.run().unwrap(), r#"Minimum: 2
+Machine 1: C[0-2] (idle: 0)
+Machine 2: A[0-1] B[1-2] (idle: 0)"#)
+    }
+
+    #[test]
+    fn run_with_json_format() {
+        let a = Task::new("A", 2, vec![]);
+        let b = Task::new("B", 1, vec!["A".to_string()]);
+
+        let scheduler = build_machine_scheduler(vec![a, b], 2);
+        let output: serde_json::Value = serde_json::from_str(&scheduler.run_with_format(Format::Json).unwrap()).unwrap();
+
+        assert_eq!(output, serde_json::json!({
+            "makespan": 3,
+            "machines": [
+                {"machine": 1, "tasks": [{"name": "A", "start_time": 0, "end_time": 2}, {"name": "B", "start_time": 2, "end_time": 3}], "idle_time": 0},
+                {"machine": 2, "tasks": [], "idle_time": 3}
+            ]
+        }));
+    }
+
+    #[test]
+    fn list_schedule_waits_for_a_free_machine() {
+        let a = Task::new("A", 3, vec![]);
+        let b = Task::new("B", 1, vec![]);
+        let c = Task::new("C", 2, vec![]);
+
+        let mut scheduler = build_machine_scheduler(vec![a, b, c], 2);
+        scheduler.list_schedule().unwrap();
+        scheduler.calculate_idle_times();
+
+        assert_eq!(scheduler.makespan, 3);
+        assert_eq!(scheduler.machines[0].tasks, [Task { name: "A".to_string(), duration: 3, start_time: 0, end_time: 3, dependencies: vec![], resources: vec![], priority: 0, estimate: None, location: None, command: None }]);
+        assert_eq!(scheduler.machines[1].tasks, [
+            Task { name: "B".to_string(), duration: 1, start_time: 0, end_time: 1, dependencies: vec![], resources: vec![], priority: 0, estimate: None, location: None, command: None },
+            Task { name: "C".to_string(), duration: 2, start_time: 1, end_time: 3, dependencies: vec![], resources: vec![], priority: 0, estimate: None, location: None, command: None }]);
+        assert_eq!(scheduler.machines[0].idle_time, 0);
+        assert_eq!(scheduler.machines[1].idle_time, 0);
+    }
+
+    #[test]
+    fn run_with_non_existing_dependencies() {
+        let a = Task::new("A", 1, vec![]);
+        let b = Task::new("B", 1, vec!["C".to_string()]);
+
+        let scheduler = build_machine_scheduler(vec![a, b], 2);
+        assert_eq!(scheduler.run(), Err(ScheduleError::UnknownDependency {
+            task: "B".to_string(),
+            dependency: "C".to_string(),
+            location: None,
+        }));
+    }
+}
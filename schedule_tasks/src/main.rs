@@ -1,96 +1,462 @@
 use std::fs::File;
-use std::io::Write;
-use regex::Regex;
+use std::io::{Read, Write};
+use std::thread;
+use std::time::{Duration, SystemTime};
 use anyhow::{bail, Context, Result};
-use clap::{arg, Command};
+use clap::{arg, Arg, Command, ErrorKind};
 use std::fs;
-use crate::scheduler::build_scheduler;
-use nom_supreme::{
-    error::ErrorTree,
-    final_parser::Location,
-};
+use schedule_tasks::diff::diff_job_schedules;
+use schedule_tasks::dot::print_dot;
+use schedule_tasks::gantt::print_svg_gantt;
+use schedule_tasks::executor::build_executor;
+use schedule_tasks::incremental::build_incremental_scheduler;
+use schedule_tasks::input::diagnostic::{diagnose, print_compatible};
+use schedule_tasks::input::include::load_job_files;
+use schedule_tasks::scheduler::{build_job_scheduler, check_job};
+use schedule_tasks::machines::build_job_machine_scheduler;
+use schedule_tasks::output::{to_json, Format, OutputOptions};
+use schedule_tasks::policy::Policy;
+use schedule_tasks::simulation::{build_simulator, Distribution};
+use schedule_tasks::state::load_state;
+use schedule_tasks::task::Job;
 
-mod input;
-mod scheduler;
-mod task;
+// how often --watch checks whether the job files changed
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
 
 fn main() -> Result<()> {
-    let matches = Command::new("schedule-tasks")
+    let mut command = Command::new("schedule-tasks")
         .version("0.1.0")
         .author("Sietse van der Bom")
         .about("App to schedule tasks.")
-        .arg(arg!([INPUT_FILE_NAME]).validator(extract_input_file_name).default_value("./test/example.tasks.in"))
+        .arg(arg!([INPUT_FILE_NAME] "Path of the job file, or - to read it from stdin").default_value("./test/example.tasks.in"))
+        .arg(arg!(-o --output [OUTPUT_FILE_NAME] "Write the output to this file instead of stdout"))
+        .arg(arg!(-m --machines [N] "Schedule on a fixed number of machines instead of unlimited computers").validator(parse_machines))
+        .arg(format_arg())
+        .arg(policy_arg("Which ready task goes first when machines or resources are limited"))
+        .arg(arg!(--cpm "Also print earliest and latest start and finish, total and free float of every task").conflicts_with("machines"))
+        .arg(arg!(--"all-critical-paths" "Print every critical path instead of one").conflicts_with("machines"))
+        .arg(arg!(--profile "Also print the number of tasks running in parallel over time").conflicts_with("machines"))
+        .arg(arg!(--gantt "Also print a Gantt chart of the schedule").conflicts_with("machines"))
+        .arg(arg!(--svg [SVG_FILE_NAME] "Also write a Gantt chart of the schedule as SVG to this file").conflicts_with("machines"))
+        .arg(arg!(--emit [WHAT] "Write the schedule, or the dependency graph as a Graphviz DOT digraph").possible_values(["schedule", "dot"]).default_value("schedule").conflicts_with("machines"))
+        .arg(arg!(--"dot-clusters" "Group the tasks starting at the same time in a cluster of the DOT digraph").conflicts_with("machines"))
+        .arg(arg!(--simulate [RUNS] "Schedule the job this number of times with durations sampled from the three-point estimates")
+            .validator(parse_runs)
+            .conflicts_with_all(&["machines", "cpm", "all-critical-paths", "profile", "gantt", "svg", "dot-clusters"]))
+        .arg(arg!(--distribution [DISTRIBUTION] "How the durations are sampled in a simulation").possible_values(["pert", "triangular"]).default_value("pert").requires("simulate"))
+        .arg(error_format_arg())
+        .arg(arg!(--watch "Keep watching the job files and print what changed in the schedule after every change")
+            .conflicts_with_all(&["machines", "simulate", "output"]))
+        .arg(arg!(--seed [SEED] "Seed of the random durations in a simulation").validator(|seed| seed.parse::<u64>()).default_value("0").requires("simulate"))
+        .subcommand(Command::new("diff")
+            .about("Compare the schedules of two versions of a job file")
+            .arg(arg!(<OLD_FILE_NAME> "Path of the old version of the job file"))
+            .arg(arg!(<NEW_FILE_NAME> "Path of the new version of the job file"))
+            .arg(format_arg())
+            .arg(policy_arg("Which ready task goes first in both schedules when resources are limited"))
+            .arg(error_format_arg())
+            .after_help(r#"Compares two versions of a job file, e.g. before and after a change in a
+pull request. Outputs:
+  - the added and removed tasks, and the tasks with another duration,
+    other dependencies or another command,
+  - the tasks that start at another time and by how much, (critical)
+    when they are on the new critical path,
+  - the minimum total duration and max-parallelism before and after,
+  - the critical path, or the old and the new one when it changed.
+  Both versions are scheduled with the same --policy.
+  With --format json the same is written as JSON.
+"#))
+        .subcommand(Command::new("exec")
+            .about("Run the commands of the tasks of a job file in dependency order")
+            .arg(arg!([INPUT_FILE_NAME] "Path of the job file").default_value("./test/example.tasks.in"))
+            .arg(arg!(-j --jobs [N] "Number of commands running at the same time, by default the number of CPUs").validator(parse_machines))
+            .arg(arg!(--state [STATE_FILE] "Path of a file recording how the tasks ended, to resume an earlier execution"))
+            .arg(format_arg())
+            .arg(policy_arg("Which ready task goes first in the plan when resources are limited"))
+            .arg(error_format_arg())
+            .after_help(r#"Runs the shell command of every task, e.g. A(1) run "make lib" after [B],
+with sh -c in the current directory. A task starts once all its
+dependencies succeeded, a worker is free and its resources fit within the
+capacities, ready tasks going in the order of the planned schedule. A task
+without a command succeeds right away. When a command fails, the tasks
+depending on it are skipped and the others still run. The output of each
+command is printed to stderr when it ends, prefixed by the task-name.
+Outputs per task the status, and the planned and actual start-time and
+duration, a time unit counting as a second. Exits with status 1 when a
+task failed.
+  With --state FILE how every task ended is saved to FILE as it ends.
+  Running again with the same FILE, e.g. after an interruption or a fixed
+  failure, reuses the tasks that succeeded and runs only the rest. A task
+  whose command, dependencies or resources changed runs again, together
+  with the tasks depending on it.
+"#))
         .after_help(r#"App to schedule tasks based on duration and dependencies.
   Outputs:
     - critical-path,
     - minimum total duration,
     - max-parallelism.
+  With --all-critical-paths every critical path is printed, and the JSON
+  output also contains the critical subgraph as a list of edges.
+  With --cpm a table follows with the Critical Path Method figures of every
+  task: earliest and latest start and finish, total and free float.
+  With --profile a table follows with the number of tasks running in
+  parallel between each two consecutive start- or end-times.
+  With --gantt a Gantt chart follows: one row per task, critical tasks
+  drawn with #, and a histogram of the tasks running in parallel. With
+  --svg FILE the same chart is written to FILE as an SVG image.
+  With --emit dot the dependency graph is written as a Graphviz DOT digraph
+  instead, with the critical edges in red; --dot-clusters groups the tasks
+  starting at the same time.
+  With --format json the full schedule is written as JSON: every task with
+  start-time, end-time, slack, CPM figures and dependencies, and the
+  parallelism profile.
+  With --machines N:
+    - minimum total duration on N machines,
+    - task-to-machine assignment,
+    - idle time per machine.
+  When tasks use resources, e.g. A(3) uses [gpu:1, mem:4] after [B], and
+  the job starts with a capacity line, e.g. capacity [gpu:2, mem:8], tasks
+  only start when their resources fit and the peak usage per resource is
+  printed. A task waiting for resources counts the tasks releasing them as
+  dependencies in the critical path and the CPM figures.
+  When machines or resources are limited, ready tasks with a higher
+  priority, e.g. A(3) priority 2 after [B], go first. Ties are broken by
+  --policy: file-order (default), longest-processing-time, most-successors
+  (most direct dependents) or critical-path-first (longest remaining chain).
+  Durations and lags may be fractional, e.g. A(1.5), or carry a unit:
+  ms, s, m or min, h and d, e.g. A(90s) after [B+1.5h]. Either every time
+  in a file has a unit or none has; with units times print like 1h30m.
+  A duration can be a three-point estimate, optimistic..likely..pessimistic,
+  e.g. A(2..3..8); the schedule uses the likely duration. With --simulate N
+  the job is scheduled N times with the estimated durations sampled from a
+  PERT (default) or triangular --distribution, reproducible by --seed; both
+  need --simulate. The output gives the P50, P90 and P99 makespan and per
+  task how often it was on a critical path. Whole time units are simulated
+  in thousandths.
+  A job file may contain blank lines and # comments, and start with header
+  lines with metadata printed above the output: name: ..., owner: ... and
+  description: ..., each given at most once.
+  The header can include other job files, e.g. include "build.tasks.in" as
+  build, relative to the including file. Their tasks are named with the
+  namespace, build::compile, and can be dependencies of tasks in any file.
+  A file included twice in the same namespace adds its tasks once, and a
+  capacity declared in several files must have the same value in each.
+  A syntax error is reported with the line in error, a caret under the
+  error and a hint where possible. With --error-format compat it is
+  reported as Error: line L, column C, kind: K instead. Parsing goes on at
+  the next task after an error, so every syntax error in every file is
+  reported, together with every duplicate task name, unknown dependency,
+  task depending on itself and dependency cycle among the tasks that did
+  parse.
+  With --watch the job file and its included files are watched after the
+  output is printed. After every change the added, removed and changed
+  tasks are printed, the tasks that start at another time, the minimum
+  duration and whether the critical path changed. Without resources only
+  the changed tasks and the tasks depending on a task that moved are
+  placed again, and their number is printed as rescheduled; with
+  resources the whole job is scheduled again. The changes are printed as
+  text to stdout, so --watch cannot be used with --output, --format json
+  or --emit dot.
+  schedule-tasks diff OLD NEW compares the schedules of two versions of a
+  job file, see schedule-tasks diff --help. A job file named diff or exec
+  in the current directory is taken for the subcommand, so give it as
+  ./diff or ./exec to schedule it.
+  A task can carry a shell command, e.g. A(1) run "make lib" after [B].
+  Within the quotes \" is a double quote and \\ a backslash, any other
+  backslash is an error.
+  schedule-tasks exec FILE runs the commands in dependency order on a pool
+  of workers and reports the planned and actual timing, see
+  schedule-tasks exec --help.
 
-"#).get_matches();
+"#);
+    let matches = command.get_matches_mut();
+    // clap only knows conflicts between arguments, not between their values
+    if matches.value_of("emit") == Some("dot") {
+        let conflicts = [("--format json", matches.value_of("format") == Some("json")), ("--simulate", matches.is_present("simulate"))];
+        if let Some((argument, _)) = conflicts.iter().find(|(_, present)| *present) {
+            command.error(ErrorKind::ArgumentConflict, format!("The argument '--emit dot' cannot be used with '{}'", argument)).exit();
+        }
+    }
+    // the changes after every update are printed as text to stdout
+    if matches.is_present("watch") {
+        let conflicts = [("--format json", matches.value_of("format") == Some("json")), ("--emit dot", matches.value_of("emit") == Some("dot"))];
+        if let Some((argument, _)) = conflicts.iter().find(|(_, present)| *present) {
+            command.error(ErrorKind::ArgumentConflict, format!("The argument '--watch' cannot be used with '{}'", argument)).exit();
+        }
+    }
 
-    fn extract_input_file_name(input: &str) -> Result<String> {
-        let name = Regex::new(r"^(./)?test/(?P<name>[0-9A-Za-z_-]+)\.?tasks.in$").unwrap();
-        let captures = name.captures(input);
+    // the arguments shared by the subcommands, with the help of --policy telling which schedule it orders
+    fn format_arg() -> Arg<'static> {
+        arg!(-f --format [FORMAT] "Output format").possible_values(["text", "json"]).default_value("text")
+    }
+
+    fn policy_arg(help: &'static str) -> Arg<'static> {
+        arg!(-p --policy [POLICY])
+            .help(help)
+            .possible_values(["file-order", "longest-processing-time", "most-successors", "critical-path-first"])
+            .default_value("file-order")
+    }
+
+    fn error_format_arg() -> Arg<'static> {
+        arg!(--"error-format" [ERROR_FORMAT] "How syntax errors are reported: quoting the line in error with hints, or the compatible one-line form")
+            .possible_values(["rich", "compat"])
+            .default_value("rich")
+    }
 
-        match captures {
-            Some(capture) => Ok(capture["name"].to_string()),
-            None => bail!("Could not parse input filename, should have format: test/<name>.tasks.in"),
+    fn read_input(path: &str) -> Result<String> {
+        let mut input = String::new();
+        if path == "-" {
+            std::io::stdin().read_to_string(&mut input)?;
+        } else {
+            input = fs::read_to_string(path)?;
+        }
+
+        Ok(input)
+    }
+
+    fn parse_machines(input: &str) -> Result<usize> {
+        match input.parse::<usize>() {
+            Ok(machines) if machines > 0 => Ok(machines),
+            _ => bail!("Number of machines should be a positive integer"),
+        }
+    }
+
+    fn parse_runs(input: &str) -> Result<usize> {
+        match input.parse::<usize>() {
+            Ok(runs) if runs > 0 => Ok(runs),
+            _ => bail!("Number of runs should be a positive integer"),
         }
     }
 
     fn make_output_file(output_text: &str, path: &str) -> Result<()> {
         let mut output = File::create(path)
             .with_context(|| format!("Failed to create file to write with path: {}", path))?;
-        write!(output, "{}", output_text).context("Failed to write output-file")?;
+        writeln!(output, "{}", output_text).context("Failed to write output-file")?;
 
         Ok(())
     }
 
+    // The job of the input file and its included files, with the paths of those files. The syntax errors of every file
+    // and the errors of the job made of the tasks that did parse are printed instead.
+    fn load_job(path: &str, input: String, compatible_errors: bool) -> Option<(Job, Vec<String>)> {
+        let job_files = match load_job_files(path, input) {
+            Ok(job_files) => job_files,
+            Err(error) => {
+                eprintln!("Error: {}", error);
+                return None;
+            }
+        };
+
+        let mut syntax_errors = vec![];
+        for file in job_files.files.iter().filter(|file| !file.syntax_errors.is_empty()) {
+            if compatible_errors {
+                // errors in the input file itself keep their location without the file name
+                let name = if file.path == path { None } else { Some(file.path.as_str()) };
+                syntax_errors.extend(file.syntax_errors.iter().map(|error| print_compatible(name, &file.text, error)));
+            } else {
+                let name = if file.path == "-" { "<stdin>" } else { file.path.as_str() };
+                syntax_errors.extend(file.syntax_errors.iter().map(|error| diagnose(&file.text, error).print(name)));
+            }
+        }
+        if !syntax_errors.is_empty() {
+            eprintln!("{}", syntax_errors.join(if compatible_errors { "\n" } else { "\n\n" }));
+        }
+
+        let (job, errors) = match job_files.job() {
+            Ok(job) => {
+                let errors = check_job(&job).into_iter().map(|error| error.to_string()).collect();
+                (Some(job), errors)
+            }
+            Err(error) => (None, vec![error.to_string()]),
+        };
+        for error in &errors {
+            eprintln!("Error: {}", error);
+        }
+        if !compatible_errors && syntax_errors.len() > 1 {
+            eprintln!("\nerror: aborting due to {} syntax errors", syntax_errors.len());
+        }
+        if !syntax_errors.is_empty() || !errors.is_empty() {
+            return None;
+        }
+
+        Some((job?, job_files.files.into_iter().map(|file| file.path).collect()))
+    }
+
+    // Polls the modification times of the job files, and after a change prints which tasks changed and moved.
+    fn watch(path: &str, mut paths: Vec<String>, job: Job, policy: Policy, compatible_errors: bool) -> Result<()> {
+        let modification_times = |paths: &[String]| -> Vec<Option<SystemTime>> {
+            paths.iter().map(|path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok()).collect()
+        };
+        let mut scheduler = build_incremental_scheduler();
+        scheduler.policy = policy;
+        scheduler.update(job)?;
+        let mut last_modified = modification_times(&paths);
+
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+            if modification_times(&paths) == last_modified {
+                continue;
+            }
+            last_modified = modification_times(&paths);
+
+            let input = match read_input(path) {
+                Ok(input) => input,
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    continue;
+                }
+            };
+            let (job, job_paths) = match load_job(path, input, compatible_errors) {
+                Some(job) => job,
+                None => continue,
+            };
+            // an include may have been added or removed
+            if job_paths != paths {
+                paths = job_paths;
+                last_modified = modification_times(&paths);
+            }
+            match scheduler.update(job) {
+                Ok(update) => println!("\n{}", update.print()),
+                Err(error) => eprintln!("Error: {}", error),
+            }
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("diff") {
+        let compatible_errors = matches.value_of("error-format") == Some("compat");
+        let mut jobs = vec![];
+        for file_name in ["OLD_FILE_NAME", "NEW_FILE_NAME"] {
+            let file_name = matches.value_of(file_name).context("Could not match cli argument")?;
+            let input = read_input(file_name)
+                .with_context(|| format!("Something went wrong reading the input file {}", file_name))?;
+            match load_job(file_name, input, compatible_errors) {
+                Some((job, _)) => jobs.push(job),
+                None => std::process::exit(1),
+            }
+        }
+        let new = jobs.pop().context("Could not load the new job file")?;
+        let old = jobs.pop().context("Could not load the old job file")?;
+        let policy: Policy = matches.value_of("policy").context("Could not match cli argument")?.parse()?;
+        let diff = match diff_job_schedules(old, new, policy) {
+            Ok(diff) => diff,
+            Err(error) => {
+                eprintln!("Error: {}", error);
+                std::process::exit(1)
+            }
+        };
+        let format: Format = matches.value_of("format").context("Could not match cli argument")?.parse()?;
+        match format {
+            Format::Text => println!("{}", diff.print()),
+            Format::Json => println!("{}", to_json(&diff)),
+        }
+        return Ok(());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("exec") {
+        let input_file_name = matches.value_of("INPUT_FILE_NAME").context("Could not match cli argument")?;
+        let input = read_input(input_file_name)
+            .context("Something went wrong reading the input file")?;
+        let (job, _) = match load_job(input_file_name, input, matches.value_of("error-format") == Some("compat")) {
+            Some(job) => job,
+            None => std::process::exit(1),
+        };
+        let workers = match matches.value_of("jobs") {
+            Some(workers) => parse_machines(workers)?,
+            None => thread::available_parallelism().map_or(1, |workers| workers.get()),
+        };
+
+        let mut scheduler = build_job_scheduler(job);
+        scheduler.policy = matches.value_of("policy").context("Could not match cli argument")?.parse()?;
+        let mut executor = build_executor(scheduler, workers);
+        if let Some(state_file) = matches.value_of("state") {
+            executor.state = load_state(state_file)?;
+            executor.state_file = Some(state_file.to_string());
+        }
+        let format: Format = matches.value_of("format").context("Could not match cli argument")?.parse()?;
+        match executor.run_with_format(format) {
+            Ok(output) => println!("{}", output),
+            Err(error) => {
+                eprintln!("Error: {}", error);
+                std::process::exit(1)
+            }
+        }
+        if !executor.succeeded() {
+            std::process::exit(1)
+        }
+        return Ok(());
+    }
+
     let input_file_name = matches.value_of("INPUT_FILE_NAME").context("Could not match cli argument")?;
-    let output_file_name = "./test/".to_string() + &extract_input_file_name(input_file_name)? + ".sched.out";
-    let input = fs::read_to_string(&input_file_name)
+    if matches.is_present("watch") && input_file_name == "-" {
+        bail!("Cannot watch stdin, give the path of the job file");
+    }
+    let input = read_input(input_file_name)
         .context("Something went wrong reading the input file")?;
 
-    let tasks = match input::parsers::parse_job(&input) {
-        Ok(tasks) => tasks,
-        Err(ErrorTree::Stack { base, contexts: _ }) => {
-            match *base {
-                ErrorTree::Base { location, kind } => {
-                    let Location { line, column } = location;
-                    eprintln!("Error: row {}, column {}, kind: {}", line, column, kind);
-                }
-                ErrorTree::Alt(bases) => {
-                    let mut bases_sorted = vec![];
-                    for base in bases {
-                        bases_sorted.insert(0, base);
-                    }
-                    for base in bases_sorted {
-                        if let ErrorTree::Base { location, kind } = base {
-                            let Location { line, column, } = location;
-                            eprintln!("Error: row {}, column {}, kind: {}", line, column, kind);
+    let compatible_errors = matches.value_of("error-format") == Some("compat");
+    let (job, paths) = match load_job(input_file_name, input, compatible_errors) {
+        Some(job) => job,
+        None => std::process::exit(1),
+    };
+    let watched_job = matches.is_present("watch").then(|| job.clone());
+
+    let format: Format = matches.value_of("format").context("Could not match cli argument")?.parse()?;
+    let policy: Policy = matches.value_of("policy").context("Could not match cli argument")?.parse()?;
+    let schedule = match matches.value_of("machines") {
+        Some(machines) => {
+            let mut scheduler = build_job_machine_scheduler(job, parse_machines(machines)?);
+            scheduler.policy = policy;
+            scheduler.run_with_format(format)
+        }
+        None => {
+            let mut scheduler = build_job_scheduler(job);
+            scheduler.policy = policy;
+            scheduler.output_options = OutputOptions {
+                timings: matches.is_present("cpm"),
+                all_critical_paths: matches.is_present("all-critical-paths"),
+                gantt: matches.is_present("gantt"),
+                profile: matches.is_present("profile"),
+            };
+            if let Some(runs) = matches.value_of("simulate") {
+                let mut simulator = build_simulator(scheduler, parse_runs(runs)?);
+                simulator.distribution = matches.value_of("distribution").context("Could not match cli argument")?.parse::<Distribution>()?;
+                simulator.seed = matches.value_of("seed").context("Could not match cli argument")?.parse()?;
+                simulator.run_with_format(format)
+            } else {
+                match scheduler.schedule() {
+                    Ok(()) => {
+                        if let Some(svg_file_name) = matches.value_of("svg") {
+                            make_output_file(&print_svg_gantt(&scheduler), svg_file_name)
+                                .with_context(|| format!("Could not make SVG file {}", svg_file_name))?;
+                        }
+                        match matches.value_of("emit") {
+                            Some("dot") => Ok(print_dot(&scheduler, matches.is_present("dot-clusters"))),
+                            _ => Ok(scheduler.print_with_format(format)),
                         }
                     }
-                    std::process::exit(1)
-                }
-                _ => {
-                    eprintln!("Error");
-                    std::process::exit(1)
+                    Err(error) => Err(error),
                 }
             }
-            std::process::exit(1)
         }
+    };
+
+    let output = match schedule {
+        Ok(output) => output,
         Err(error) => {
-            eprintln!("Error: {:#?}", error);
+            eprintln!("Error: {}", error);
             std::process::exit(1)
         }
     };
 
-    let scheduler = build_scheduler(tasks);
-    let output = scheduler.run();
-    eprintln!("{}", output);
+    match matches.value_of("output") {
+        Some(output_file_name) => make_output_file(output.as_str(), output_file_name)
+            .with_context(|| format!("Could not make output file {}", output_file_name))?,
+        None => println!("{}", output),
+    }
 
-    make_output_file(output.as_str(), &output_file_name)
-        .with_context(|| format!("Could not make output file {}", &output_file_name))?;
+    if let Some(job) = watched_job {
+        watch(input_file_name, paths, job, policy, compatible_errors)?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,240 @@
+use std::str::FromStr;
+use anyhow::{bail, Error};
+use serde::Serialize;
+use crate::task::{
+    Duration,
+    Metadata,
+    ResourceAmount,
+    TimeMoment,
+};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => bail!("Unknown output format {}, should be text or json", input),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OutputOptions {
+    pub timings: bool,
+    pub all_critical_paths: bool,
+    pub gantt: bool,
+    pub profile: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScheduledTaskOutput<'a> {
+    pub name: &'a str,
+    pub duration: Duration,
+    pub start_time: TimeMoment,
+    pub end_time: TimeMoment,
+    pub slack: Duration,
+    pub earliest_start: TimeMoment,
+    pub earliest_finish: TimeMoment,
+    pub latest_start: TimeMoment,
+    pub latest_finish: TimeMoment,
+    pub total_float: Duration,
+    pub free_float: Duration,
+    pub dependencies: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MetadataOutput<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<&'a str>,
+}
+
+pub fn build_metadata_output(metadata: &Metadata) -> Option<MetadataOutput<'_>> {
+    (metadata.name.is_some() || metadata.owner.is_some() || metadata.description.is_some()).then_some(MetadataOutput {
+        name: metadata.name.as_deref(),
+        owner: metadata.owner.as_deref(),
+        description: metadata.description.as_deref(),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScheduleOutput<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<MetadataOutput<'a>>,
+    pub tasks: Vec<ScheduledTaskOutput<'a>>,
+    pub critical_path: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub critical_paths: Option<Vec<Vec<&'a str>>>,
+    // the critical paths only list the first ones
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub critical_paths_truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub critical_edges: Option<Vec<(&'a str, &'a str)>>,
+    pub makespan: TimeMoment,
+    // the length of a tick in which all times are given, left out when they count in time units
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tick: Option<&'static str>,
+    pub max_parallelism: usize,
+    pub parallelism_profile: Vec<ParallelismIntervalOutput>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub resources: Vec<ResourceOutput>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParallelismIntervalOutput {
+    pub from: TimeMoment,
+    pub to: TimeMoment,
+    pub tasks: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResourceOutput {
+    pub name: String,
+    pub capacity: Option<ResourceAmount>,
+    pub peak_usage: ResourceAmount,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MachineTaskOutput<'a> {
+    pub name: &'a str,
+    pub start_time: TimeMoment,
+    pub end_time: TimeMoment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MachineOutput<'a> {
+    pub machine: usize,
+    pub tasks: Vec<MachineTaskOutput<'a>>,
+    pub idle_time: TimeMoment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MachineScheduleOutput<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<MetadataOutput<'a>>,
+    pub makespan: TimeMoment,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tick: Option<&'static str>,
+    pub machines: Vec<MachineOutput<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub resources: Vec<ResourceOutput>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PercentileOutput {
+    pub percentile: usize,
+    pub makespan: TimeMoment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CriticalityOutput<'a> {
+    pub name: &'a str,
+    pub critical_runs: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SimulationOutput<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<MetadataOutput<'a>>,
+    pub runs: usize,
+    pub distribution: &'static str,
+    pub seed: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tick: Option<&'static str>,
+    pub makespan_percentiles: Vec<PercentileOutput>,
+    pub criticality: Vec<CriticalityOutput<'a>>,
+}
+
+// times in milliseconds since the start of the execution, a time unit counting as a second
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExecutedTaskOutput<'a> {
+    pub name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<&'a str>,
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<&'a str>,
+    pub planned_start: TimeMoment,
+    pub planned_duration: Duration,
+    pub actual_start: Option<TimeMoment>,
+    pub actual_duration: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExecutionOutput<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<MetadataOutput<'a>>,
+    pub workers: usize,
+    pub tasks: Vec<ExecutedTaskOutput<'a>>,
+    pub planned_makespan: TimeMoment,
+    pub actual_makespan: TimeMoment,
+}
+
+pub fn to_json<T: Serialize>(output: &T) -> String {
+    serde_json::to_string_pretty(output).expect("schedule output only contains strings and numbers")
+}
+
+// left-aligned columns, each as wide as its widest cell
+pub fn print_table(rows: Vec<Vec<String>>) -> String {
+    let mut widths: Vec<usize> = vec![];
+    for row in &rows {
+        for (column, cell) in row.iter().enumerate() {
+            if column == widths.len() {
+                widths.push(0);
+            }
+            widths[column] = widths[column].max(cell.chars().count());
+        }
+    }
+
+    rows.iter()
+        .map(|row| row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_from_str() {
+        assert_eq!("text".parse::<Format>().unwrap(), Format::Text);
+        assert_eq!("json".parse::<Format>().unwrap(), Format::Json);
+        assert!("xml".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn print_table_aligns_columns() {
+        let rows = vec![
+            vec!["Task".to_string(), "ES".to_string(), "Free float".to_string()],
+            vec!["Compile".to_string(), "120".to_string(), "0".to_string()],
+        ];
+        assert_eq!(print_table(rows), "Task     ES   Free float\nCompile  120  0");
+    }
+
+    #[test]
+    fn metadata_output_only_when_set() {
+        let metadata = Metadata { name: Some("Release".to_string()), owner: None, description: None };
+        assert_eq!(build_metadata_output(&metadata), Some(MetadataOutput { name: Some("Release"), owner: None, description: None }));
+        assert_eq!(build_metadata_output(&Metadata::default()), None);
+    }
+}
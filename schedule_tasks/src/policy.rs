@@ -0,0 +1,131 @@
+use std::cmp::Reverse;
+use std::str::FromStr;
+use anyhow::{bail, Error};
+use crate::graph::Graph;
+use crate::task::{
+    Duration,
+    Task,
+};
+
+
+// Decides which of the ready tasks goes first when machines or resources are limited. A higher task priority
+// always goes first, the policy breaks the ties between tasks of the same priority, and file order the remaining ties.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    #[default]
+    FileOrder,
+    LongestProcessingTime,
+    MostSuccessors,
+    CriticalPathFirst,
+}
+
+impl FromStr for Policy {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "file-order" => Ok(Policy::FileOrder),
+            "longest-processing-time" => Ok(Policy::LongestProcessingTime),
+            "most-successors" => Ok(Policy::MostSuccessors),
+            "critical-path-first" => Ok(Policy::CriticalPathFirst),
+            _ => bail!("Unknown policy {}, should be file-order, longest-processing-time, most-successors or critical-path-first", input),
+        }
+    }
+}
+
+// The longest time from the start of each task to the end of the job, by task index. Each dependent starts as early
+// after the task as its dependency type and lag allow, as in the forward pass of the scheduler, so the chain of a
+// start-to-start dependency overlaps the task and a lag lengthens it.
+fn remaining_path_lengths(tasks: &[Task], graph: &Graph) -> Vec<Duration> {
+    let mut lengths: Vec<isize> = tasks.iter().map(|task| task.duration as isize).collect();
+    // in reverse topological order every dependent of a task has its length before the task is reached
+    for &id in graph.topological_order.iter().rev() {
+        let task = &tasks[id.index()];
+        for dependency in &task.dependencies {
+            if let Some(dependency_id) = graph.index_of(&dependency.name) {
+                let dependency_task = &tasks[dependency_id.index()];
+                let start_after = dependency.earliest_start(0, dependency_task.duration, task.duration);
+                let length = start_after + lengths[id.index()];
+                lengths[dependency_id.index()] = lengths[dependency_id.index()].max(length);
+            }
+        }
+    }
+    lengths.into_iter().map(|length| length as Duration).collect()
+}
+
+// The task indices in the order in which ready tasks are taken.
+pub fn order_tasks(tasks: &[Task], graph: &Graph, policy: Policy) -> Vec<usize> {
+    let policy_keys: Vec<Reverse<usize>> = match policy {
+        Policy::FileOrder => vec![Reverse(0); tasks.len()],
+        Policy::LongestProcessingTime => tasks.iter().map(|task| Reverse(task.duration)).collect(),
+        Policy::MostSuccessors => graph.dependents.iter().map(|dependents| Reverse(dependents.len())).collect(),
+        Policy::CriticalPathFirst => remaining_path_lengths(tasks, graph).into_iter().map(Reverse).collect(),
+    };
+
+    let mut order: Vec<usize> = (0..tasks.len()).collect();
+    order.sort_by_key(|&index| (Reverse(tasks[index].priority), policy_keys[index], index));
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::build_graph;
+    use crate::task::{
+        Dependency,
+        DependencyKind,
+    };
+
+    fn tasks() -> Vec<Task> {
+        vec![
+            Task::new("A", 1, vec![]),
+            Task::new("B", 2, vec![]),
+            Task::new("C", 1, vec![]),
+            Task::new("D", 3, vec!["A".to_string()]),
+            Task::new("E", 1, vec!["C".to_string()]),
+            Task::new("F", 1, vec!["C".to_string()]),
+        ]
+    }
+
+    #[test]
+    fn policy_from_str() {
+        assert_eq!("critical-path-first".parse::<Policy>().unwrap(), Policy::CriticalPathFirst);
+        assert!("random".parse::<Policy>().is_err());
+    }
+
+    #[test]
+    fn order_tasks_by_policy() {
+        let tasks = tasks();
+        let graph = build_graph(&tasks).unwrap();
+
+        assert_eq!(order_tasks(&tasks, &graph, Policy::FileOrder), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(order_tasks(&tasks, &graph, Policy::LongestProcessingTime), vec![3, 1, 0, 2, 4, 5]);
+        assert_eq!(order_tasks(&tasks, &graph, Policy::MostSuccessors), vec![2, 0, 1, 3, 4, 5]);
+        assert_eq!(order_tasks(&tasks, &graph, Policy::CriticalPathFirst), vec![0, 3, 1, 2, 4, 5]);
+    }
+
+    #[test]
+    fn order_tasks_by_critical_path_with_dependency_types_and_lags() {
+        // C only has to finish after B, so B does not head a longer path than A
+        let finish_to_finish = Dependency { name: "B".to_string(), kind: DependencyKind::FinishToFinish, ..Default::default() };
+        let tasks = vec![Task::new("A", 3, vec![]), Task::new("B", 1, vec![]), Task::new("C", 3, vec![]).after(vec![finish_to_finish])];
+        let graph = build_graph(&tasks).unwrap();
+        assert_eq!(order_tasks(&tasks, &graph, Policy::CriticalPathFirst), vec![0, 2, 1]);
+
+        // C starts 3 after A ends, so A heads a path of 5
+        let lagged = Dependency { name: "A".to_string(), lag: 3, ..Default::default() };
+        let tasks = vec![Task::new("A", 1, vec![]), Task::new("B", 3, vec![]), Task::new("C", 1, vec![]).after(vec![lagged])];
+        let graph = build_graph(&tasks).unwrap();
+        assert_eq!(order_tasks(&tasks, &graph, Policy::CriticalPathFirst), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn order_tasks_by_priority_first() {
+        let mut tasks = tasks();
+        tasks[5].priority = 2;
+        tasks[1].priority = 1;
+        let graph = build_graph(&tasks).unwrap();
+
+        assert_eq!(order_tasks(&tasks, &graph, Policy::LongestProcessingTime), vec![5, 1, 3, 0, 2, 4]);
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::{
+    BTreeMap,
+    BTreeSet,
+};
+use crate::error::ScheduleError;
+use crate::output::ResourceOutput;
+use crate::task::{
+    Job,
+    ResourceAmount,
+    Task,
+};
+
+
+// Keeps track of the resources in use while tasks are started and finished. A resource without a declared
+// capacity is unlimited, its usage is only reported.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ResourcePool {
+    pub capacities: BTreeMap<String, ResourceAmount>,
+    pub in_use: BTreeMap<String, ResourceAmount>,
+    pub peak_usage: BTreeMap<String, ResourceAmount>,
+}
+
+impl ResourcePool {
+    pub fn check_capacities(&self, tasks: &[Task]) -> Result<(), ScheduleError> {
+        match self.capacity_errors(tasks).into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    // every task that needs more of a resource than its capacity, as it could never start
+    pub fn capacity_errors(&self, tasks: &[Task]) -> Vec<ScheduleError> {
+        let mut errors = vec![];
+        for task in tasks {
+            for (resource, required) in required_resources(task) {
+                match self.capacities.get(resource) {
+                    Some(&capacity) if required > capacity => errors.push(ScheduleError::CapacityExceeded {
+                        task: task.name.to_string(),
+                        resource: resource.to_string(),
+                        required,
+                        capacity,
+                        location: task.location.clone(),
+                    }),
+                    _ => {}
+                }
+            }
+        }
+        errors
+    }
+
+    pub fn fits(&self, task: &Task) -> bool {
+        required_resources(task).into_iter().all(|(resource, required)| match self.capacities.get(resource) {
+            Some(capacity) => self.in_use.get(resource).copied().unwrap_or(0) + required <= *capacity,
+            None => true,
+        })
+    }
+
+    // whether the task and the other task both use a resource with a capacity, so one can keep the other waiting
+    pub fn compete(&self, task: &Task, other: &Task) -> bool {
+        task.resources.iter().any(|(resource, _)| {
+            self.capacities.contains_key(resource) && other.resources.iter().any(|(other_resource, _)| other_resource == resource)
+        })
+    }
+
+    pub fn acquire(&mut self, task: &Task) {
+        for (resource, required) in &task.resources {
+            let in_use = self.in_use.entry(resource.clone()).or_insert(0);
+            *in_use += required;
+            let peak_usage = self.peak_usage.entry(resource.clone()).or_insert(0);
+            *peak_usage = (*peak_usage).max(*in_use);
+        }
+    }
+
+    pub fn release(&mut self, task: &Task) {
+        for (resource, required) in &task.resources {
+            if let Some(in_use) = self.in_use.get_mut(resource) {
+                *in_use -= required;
+            }
+        }
+    }
+
+    // every declared or used resource in alphabetical order
+    fn resource_names(&self) -> Vec<&String> {
+        let mut names: Vec<&String> = self.capacities.keys().chain(self.peak_usage.keys()).collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    pub fn print_peak_usage(&self) -> String {
+        self.resource_names()
+            .into_iter()
+            .map(|name| {
+                let peak_usage = self.peak_usage.get(name).copied().unwrap_or(0);
+                match self.capacities.get(name) {
+                    Some(capacity) => format!("{} {} of {}", name, peak_usage, capacity),
+                    None => format!("{} {}", name, peak_usage),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    pub fn to_output(&self) -> Vec<ResourceOutput> {
+        self.resource_names()
+            .into_iter()
+            .map(|name| ResourceOutput {
+                name: name.clone(),
+                capacity: self.capacities.get(name).copied(),
+                peak_usage: self.peak_usage.get(name).copied().unwrap_or(0),
+            })
+            .collect()
+    }
+}
+
+// a task can name a resource more than once, uses [gpu:1, gpu:1] needing 2 gpu
+fn required_resources(task: &Task) -> BTreeMap<&str, ResourceAmount> {
+    let mut required: BTreeMap<&str, ResourceAmount> = BTreeMap::new();
+    for (resource, amount) in &task.resources {
+        *required.entry(resource.as_str()).or_insert(0) += amount;
+    }
+    required
+}
+
+// Every resource of which the job declares the capacity more than once, as it is unclear which one is meant.
+pub fn check_declared_capacities(job: &Job) -> Vec<ScheduleError> {
+    let mut declared = BTreeSet::new();
+    let mut duplicates = BTreeSet::new();
+    for (resource, _) in &job.capacities {
+        if !declared.insert(resource) {
+            duplicates.insert(resource);
+        }
+    }
+
+    duplicates
+        .into_iter()
+        .map(|resource| ScheduleError::DuplicateCapacity {
+            resource: resource.clone(),
+            locations: vec![job.capacity_location.clone()],
+        })
+        .collect()
+}
+
+pub fn build_resource_pool(capacities: &[(String, ResourceAmount)]) -> ResourcePool {
+    ResourcePool {
+        capacities: capacities.iter().cloned().collect(),
+        in_use: BTreeMap::new(),
+        peak_usage: BTreeMap::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::SourceLocation;
+
+    #[test]
+    fn acquire_and_release() {
+        let a = Task::new("A", 1, vec![]).using(vec![("gpu".to_string(), 1), ("mem".to_string(), 4)]);
+        let b = Task::new("B", 1, vec![]).using(vec![("gpu".to_string(), 1)]);
+        let c = Task::new("C", 1, vec![]).using(vec![("gpu".to_string(), 1)]);
+
+        let mut pool = build_resource_pool(&[("gpu".to_string(), 2)]);
+        assert!(pool.fits(&a));
+        pool.acquire(&a);
+        assert!(pool.fits(&b));
+        pool.acquire(&b);
+        assert!(!pool.fits(&c));
+        pool.release(&a);
+        assert!(pool.fits(&c));
+
+        assert_eq!(pool.print_peak_usage(), "gpu 2 of 2, mem 4");
+    }
+
+    #[test]
+    fn check_capacities() {
+        let a = Task::new("A", 1, vec![]).using(vec![("gpu".to_string(), 3)]).at(2, 1);
+
+        let pool = build_resource_pool(&[("gpu".to_string(), 2)]);
+        assert_eq!(pool.check_capacities(&[a]), Err(ScheduleError::CapacityExceeded {
+            task: "A".to_string(),
+            resource: "gpu".to_string(),
+            required: 3,
+            capacity: 2,
+            location: Some(SourceLocation::new(2, 1)),
+        }));
+
+        // the same resource named twice needs both amounts at once
+        let b = Task::new("B", 1, vec![]).using(vec![("gpu".to_string(), 1), ("gpu".to_string(), 2)]);
+        assert!(pool.check_capacities(std::slice::from_ref(&b)).is_err());
+        assert!(!pool.fits(&b));
+    }
+
+    #[test]
+    fn check_declared_capacities_once() {
+        let job = Job { capacity_location: Some(SourceLocation::new(1, 1)), ..Job::default() }
+            .with_capacity("gpu", 2)
+            .with_capacity("mem", 8)
+            .with_capacity("gpu", 3);
+
+        let errors = check_declared_capacities(&job);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].to_string(), "capacity of gpu is declared more than once (line 1, column 1)");
+        assert!(check_declared_capacities(&Job::default().with_capacity("gpu", 2)).is_empty());
+    }
+}
@@ -1,173 +1,571 @@
-use std::borrow::Borrow;
-use std::collections::BTreeSet;
+use std::cmp::Reverse;
+use std::collections::{
+    BinaryHeap,
+    BTreeMap,
+    BTreeSet,
+};
 use std::iter::once;
+use crate::error::{
+    JobError,
+    ScheduleError,
+};
+use crate::gantt::print_ascii_gantt;
+use crate::graph::{
+    build_graph,
+    check_tasks,
+    resolve_dependencies,
+    Graph,
+};
+use crate::output::{
+    build_metadata_output,
+    print_table,
+    to_json,
+    Format,
+    OutputOptions,
+    ParallelismIntervalOutput,
+    ScheduleOutput,
+    ScheduledTaskOutput,
+};
+use crate::policy::{
+    order_tasks,
+    Policy,
+};
+use crate::resources::{
+    build_resource_pool,
+    check_declared_capacities,
+    ResourcePool,
+};
 use crate::task::{
+    Dependency,
+    DependencyKind,
+    Duration,
+    Job,
+    Metadata,
+    ParallelismInterval,
+    Schedule,
+    ScheduledTask,
     Task,
+    TaskId,
+    TaskTiming,
     TimeMoment,
 };
+use crate::time::TimeScale;
 
 
 #[derive(Debug, Clone)]
-pub struct Scheduler<'a> {
-    pub number_of_unscheduled_tasks: usize,
-    pub unscheduled_tasks: Vec<Task<'a>>,
-    pub scheduled_tasks: Vec<Task<'a>>,
-    pub critical_path: Vec<&'a str>,
-    pub last_task: Task<'a>,
+pub struct Scheduler {
+    pub unscheduled_tasks: Vec<Task>,
+    pub graph: Graph,
+    pub scheduled_tasks: Vec<Task>,
+    pub timings: Vec<TaskTiming>,
+    // the task ids from the last task of the critical path back to the first
+    pub critical_path: Vec<TaskId>,
+    // by task id, the tasks that ended right when it could start after waiting for their resources
+    pub resource_dependencies: Vec<Vec<TaskId>>,
+    pub last_task: Task,
     pub scheduled_tasks_time_nodes: BTreeSet<TimeMoment>,
+    pub parallelism_profile: Vec<ParallelismInterval>,
     pub max_parallelism: usize,
+    pub resources: ResourcePool,
+    pub policy: Policy,
+    pub time_scale: TimeScale,
+    pub metadata: Metadata,
+    pub output_options: OutputOptions,
 }
 
-impl<'a> Scheduler<'a> {
-    pub fn run(mut self) -> String {
-        while self.number_of_unscheduled_tasks > 0 {
-            self.process_unscheduled_tasks();
+// enumerating every critical path is exponential in the worst case, so it stops at this number of paths
+const MAX_CRITICAL_PATHS: usize = 1000;
+
+impl Scheduler {
+    pub fn run(self) -> Result<String, ScheduleError> {
+        self.run_with_format(Format::Text)
+    }
+
+    pub fn run_with_format(mut self, format: Format) -> Result<String, ScheduleError> {
+        self.schedule()?;
+        Ok(self.print_with_format(format))
+    }
+
+    pub fn print_with_format(&self, format: Format) -> String {
+        match format {
+            Format::Text => self.print_output(),
+            Format::Json => self.print_json_output(),
         }
+    }
+
+    pub fn schedule(&mut self) -> Result<(), ScheduleError> {
+        self.schedule_tasks()?;
+        self.analyse_schedule();
+        Ok(())
+    }
+
+    fn analyse_schedule(&mut self) {
+        self.calculate_timings();
         self.calculate_parallelism();
         self.assemble_critical_path_tasks();
-        self.print_output()
     }
 
-    fn process_unscheduled_tasks(&mut self) {
-        let mut progress = false;
-        let mut start_time: Option<TimeMoment> = Some(0);
+    pub fn schedule_tasks(&mut self) -> Result<(), ScheduleError> {
+        self.graph = build_graph(&self.unscheduled_tasks)?;
+        resolve_dependencies(&mut self.unscheduled_tasks, &self.graph.names);
+        self.resources.check_capacities(&self.unscheduled_tasks)?;
+        self.resource_dependencies = vec![vec![]; self.unscheduled_tasks.len()];
 
-        for i in 0..self.unscheduled_tasks.len() {
-            if self.taskname_is_not_unique(&self.unscheduled_tasks[i]) {
-                panic!("The taskname {} is not unique", &self.unscheduled_tasks[i].name);
-            }
+        let start_times = if self.uses_resources() {
+            self.start_times_within_capacities().into_iter().map(Some).collect()
+        } else {
+            vec![None; self.unscheduled_tasks.len()]
+        };
+        self.place_tasks(&start_times);
 
-            if self.unscheduled_tasks[i].dependencies.is_empty() {
-                self.set_start_and_end_times(i, &start_time);
-                progress = true;
-                break;
-            } else {
-                let available_start_time = self.start_time_to_be_scheduled_at(&self.unscheduled_tasks[i]);
-                if let Some(time) = available_start_time {
-                    start_time = Some(time);
-                    self.set_start_and_end_times(i, &start_time);
-                    progress = true;
+        Ok(())
+    }
+
+    fn uses_resources(&self) -> bool {
+        self.unscheduled_tasks.iter().any(|task| !task.resources.is_empty())
+    }
+
+    // Schedules the tasks in topological order, at the given start-time by task index or else as soon as their
+    // dependencies allow.
+    fn place_tasks(&mut self, fixed_start_times: &[Option<TimeMoment>]) {
+        // start- and end-times by task index, so every dependency lookup is a single index instead of a scan
+        let mut start_times: Vec<TimeMoment> = vec![0; self.unscheduled_tasks.len()];
+        let mut end_times: Vec<TimeMoment> = vec![0; self.unscheduled_tasks.len()];
+        self.scheduled_tasks.reserve(self.unscheduled_tasks.len());
+
+        for index in self.graph.topological_order.iter().map(|id| id.index()) {
+            let mut scheduled_task = self.unscheduled_tasks[index].clone();
+            let start_time = match fixed_start_times[index] {
+                Some(start_time) => start_time,
+                None => scheduled_task.earliest_start(&start_times, &end_times),
+            };
+
+            scheduled_task.start_time = start_time;
+            scheduled_task.end_time = start_time + scheduled_task.duration;
+            start_times[index] = scheduled_task.start_time;
+            end_times[index] = scheduled_task.end_time;
+            // with lags and other dependency types a task can also start when no other task ends
+            self.scheduled_tasks_time_nodes.insert(scheduled_task.start_time);
+            self.scheduled_tasks_time_nodes.insert(scheduled_task.end_time);
+
+            if scheduled_task.end_time > self.last_task.end_time {
+                self.last_task = scheduled_task.clone();
+            };
+            self.scheduled_tasks.push(scheduled_task);
+        }
+    }
+
+    // List scheduling: the ready tasks are started in the order of the policy as long as their resources fit. A task
+    // is ready once all its dependencies have started, as their start- and end-times then tell when it may start.
+    // A task that has to wait for resources does not hold back the ready tasks after it. When it can start, the tasks
+    // that just ended and released a resource it uses become its resource dependencies, as delaying them would delay it.
+    fn start_times_within_capacities(&mut self) -> Vec<TimeMoment> {
+        let tasks = &self.unscheduled_tasks;
+        let order = order_tasks(tasks, &self.graph, self.policy);
+        let mut rank: Vec<usize> = vec![0; tasks.len()];
+        for (position, &index) in order.iter().enumerate() {
+            rank[index] = position;
+        }
+
+        // the ready tasks by rank, so iterating them follows the order of the policy
+        let mut number_of_unstarted_dependencies = self.graph.number_of_dependencies();
+        let mut ready: BTreeSet<usize> = (0..tasks.len()).filter(|&index| number_of_unstarted_dependencies[index] == 0).map(|index| rank[index]).collect();
+        let mut ready_at: Vec<TimeMoment> = vec![0; tasks.len()];
+        let mut running: BinaryHeap<Reverse<(TimeMoment, usize)>> = BinaryHeap::new();
+        let mut start_times: Vec<TimeMoment> = vec![0; tasks.len()];
+        let mut end_times: Vec<TimeMoment> = vec![0; tasks.len()];
+        let mut now: TimeMoment = 0;
+        // the tasks that ended at this moment, after running for some time
+        let mut released: Vec<usize> = vec![];
+
+        loop {
+            while let Some(&Reverse((end_time, finished))) = running.peek() {
+                if end_time > now {
                     break;
                 }
+                running.pop();
+                self.resources.release(&tasks[finished]);
+                if tasks[finished].duration > 0 {
+                    released.push(finished);
+                }
             }
-        };
 
-        assert!(progress, "Progress stopped while scheduling: the input file contains logical \
-        inconsistencies, like non-existent dependencies, or no tasks without dependencies, etc.");
-
-        self.set_number_of_unscheduled_tasks();
-    }
-
-    fn start_time_to_be_scheduled_at(&self, unscheduled_task: &Task) -> Option<TimeMoment> {
-        let mut latest_end_time_of_dependencies = 0;
-        let mut number_of_dependencies: usize;
-        let mut number_of_fulfilled_dependencies: usize = 0;
-        let mut dependencies_fulfilled = false;
-        'dependencies: for i in 0..unscheduled_task.dependencies.len() {
-            number_of_dependencies = unscheduled_task.dependencies.len();
-            for j in 0..self.scheduled_tasks.len() {
-                if unscheduled_task.dependencies[i] == self.scheduled_tasks[j].name {
-                    number_of_fulfilled_dependencies += 1;
-                    if self.scheduled_tasks[j].end_time >= latest_end_time_of_dependencies {
-                        latest_end_time_of_dependencies = self.scheduled_tasks[j].end_time;
-                    };
-                    if number_of_fulfilled_dependencies == number_of_dependencies {
-                        dependencies_fulfilled = true;
-                        break 'dependencies;
+            let mut started = vec![];
+            for &task_rank in &ready {
+                let index = order[task_rank];
+                if ready_at[index] <= now && self.resources.fits(&tasks[index]) {
+                    // it could have started before, but its resources did not fit until these tasks ended
+                    if ready_at[index] < now {
+                        self.resource_dependencies[index] = released
+                            .iter()
+                            .copied()
+                            .filter(|&finished| self.resources.compete(&tasks[index], &tasks[finished]))
+                            .map(TaskId)
+                            .collect();
                     }
+                    self.resources.acquire(&tasks[index]);
+                    start_times[index] = now;
+                    end_times[index] = now + tasks[index].duration;
+                    running.push(Reverse((end_times[index], index)));
+                    started.push(task_rank);
                 }
             }
+            for &task_rank in &started {
+                ready.remove(&task_rank);
+                for dependent in self.graph.dependents[order[task_rank]].iter().map(|id| id.index()) {
+                    number_of_unstarted_dependencies[dependent] -= 1;
+                    if number_of_unstarted_dependencies[dependent] == 0 {
+                        // a task can not start before it is ready, even when its dependencies would allow it
+                        ready_at[dependent] = tasks[dependent].earliest_start(&start_times, &end_times).max(now);
+                        ready.insert(rank[dependent]);
+                    }
+                }
+            }
+            // tasks that just became ready, or that end right away, can let other tasks start at this same moment
+            if !started.is_empty() {
+                continue;
+            }
+
+            // The capacities have been checked, so with nothing running every ready task that may start would have
+            // been started. The next moment something changes is when a task ends or a ready task may start.
+            let next_end = running.peek().map(|&Reverse((end_time, _))| end_time);
+            let next_ready = ready.iter().map(|&task_rank| ready_at[order[task_rank]]).filter(|&time| time > now).min();
+            now = match (next_end, next_ready) {
+                (Some(end_time), Some(ready_time)) => end_time.min(ready_time),
+                (Some(time), None) | (None, Some(time)) => time,
+                (None, None) => break,
+            };
+            released.clear();
         }
 
-        if dependencies_fulfilled { Some(latest_end_time_of_dependencies) } else { None }
+        start_times
     }
 
-    fn set_start_and_end_times(&mut self, index: usize, start_time: &Option<TimeMoment>) {
-        let mut scheduled_task = self.unscheduled_tasks[index].clone();
-        scheduled_task.start_time = start_time.unwrap_or(0);
-        scheduled_task.end_time = scheduled_task.start_time + scheduled_task.duration;
-        self.scheduled_tasks_time_nodes.insert(scheduled_task.end_time);
+    // The forward pass gave the earliest start- and end-times, the backward pass gives the latest ones. Every task
+    // limits the latest finish of its dependencies, depending on the dependency type and lag, and of its resource
+    // dependencies as finish-to-start.
+    fn calculate_timings(&mut self) {
+        let makespan = self.last_task.end_time;
+        let position_in_schedule = self.graph.topological_positions();
+        let mut latest_finishes: Vec<isize> = vec![makespan as isize; self.scheduled_tasks.len()];
+        let mut free_floats: Vec<isize> = self.scheduled_tasks.iter().map(|task| (makespan - task.end_time) as isize).collect();
+
+        // Within capacities no task starts before its dependencies, and a resource dependency starts before the task
+        // it holds up, so by start-time the dependencies of a task always come first.
+        let mut positions: Vec<usize> = (0..self.scheduled_tasks.len()).collect();
+        if self.resource_dependencies.iter().any(|dependencies| !dependencies.is_empty()) {
+            positions.sort_by_key(|&position| (self.scheduled_tasks[position].start_time, position));
+        }
 
-        self.set_last_task(scheduled_task.clone());
+        for position in positions.into_iter().rev() {
+            let id = self.graph.topological_order[position];
+            let task = &self.scheduled_tasks[position];
+            let latest_finish = latest_finishes[position];
+            let latest_start = latest_finish - task.duration as isize;
+
+            for (dependency, dependency_id) in task.dependencies.iter().filter_map(|dependency| Some((dependency, dependency.id?))) {
+                let dependency_position = position_in_schedule[dependency_id.index()];
+                let dependency_task = &self.scheduled_tasks[dependency_position];
+                let dependency_latest_finish = match dependency.kind {
+                    DependencyKind::FinishToStart => latest_start - dependency.lag,
+                    DependencyKind::StartToStart => latest_start - dependency.lag + dependency_task.duration as isize,
+                    DependencyKind::FinishToFinish => latest_finish - dependency.lag,
+                };
+                latest_finishes[dependency_position] = latest_finishes[dependency_position].min(dependency_latest_finish);
 
-        self.scheduled_tasks.push(scheduled_task);
-        self.unscheduled_tasks.remove(index);
-    }
+                let slack = task.start_time as isize - dependency.earliest_start(dependency_task.start_time, dependency_task.end_time, task.duration);
+                free_floats[dependency_position] = free_floats[dependency_position].min(slack);
+            }
+            for &dependency_id in self.resource_dependencies(id) {
+                let dependency_position = position_in_schedule[dependency_id.index()];
+                latest_finishes[dependency_position] = latest_finishes[dependency_position].min(latest_start);
+                free_floats[dependency_position] = free_floats[dependency_position].min(task.start_time as isize - self.scheduled_tasks[dependency_position].end_time as isize);
+            }
+        }
 
-    fn initialize(&mut self) {
-        self.set_number_of_unscheduled_tasks();
+        self.timings = self.scheduled_tasks
+            .iter()
+            .zip(latest_finishes.into_iter().zip(free_floats))
+            .map(|(task, (latest_finish, free_float))| TaskTiming {
+                earliest_start: task.start_time,
+                earliest_finish: task.end_time,
+                latest_start: latest_finish as TimeMoment - task.duration,
+                latest_finish: latest_finish as TimeMoment,
+                total_float: latest_finish as TimeMoment - task.end_time,
+                free_float: free_float as Duration,
+            })
+            .collect();
     }
 
-    fn taskname_is_not_unique(&self, candidate: &Task) -> bool {
-        if self.scheduled_tasks.iter().any(|task| task.name == candidate.name) {
-            return true;
+    // Sweep line over the time nodes: every task adds one running task at its start and removes it at its end,
+    // so the number of running tasks in each interval is the running sum of these changes.
+    fn calculate_parallelism(&mut self) {
+        let mut changes: BTreeMap<TimeMoment, isize> = BTreeMap::new();
+        for task in self.scheduled_tasks.iter().filter(|task| task.duration > 0) {
+            *changes.entry(task.start_time).or_insert(0) += 1;
+            *changes.entry(task.end_time).or_insert(0) -= 1;
         }
-        false
+
+        let mut running: isize = 0;
+        let time_nodes: Vec<TimeMoment> = self.scheduled_tasks_time_nodes.iter().copied().collect();
+        self.parallelism_profile = time_nodes
+            .windows(2)
+            .map(|node| {
+                running += changes.get(&node[0]).copied().unwrap_or(0);
+                ParallelismInterval { from: node[0], to: node[1], tasks: running as usize }
+            })
+            .collect();
+        self.max_parallelism = self.parallelism_profile.iter().map(|interval| interval.tasks).max().unwrap_or(0);
+    }
+
+    fn assemble_critical_path_tasks(&mut self) {
+        let position_in_schedule = self.graph.topological_positions();
+        self.assemble_critical_path(&position_in_schedule);
     }
 
-    fn set_last_task(&mut self, task: Task<'a>) {
-        if &task.end_time > self.last_task.end_time.borrow() {
-            self.last_task = task;
+    // The critical path from the last task back, with the position in the schedule of every task by task index, which
+    // an incremental update keeps instead of deriving it from the topological order again.
+    pub fn assemble_critical_path(&mut self, position_in_schedule: &[usize]) {
+        self.critical_path.clear();
+        let scheduled_task = |id: TaskId| &self.scheduled_tasks[position_in_schedule[id.index()]];
+
+        let mut critical_path_task = match self.graph.index_of(&self.last_task.name) {
+            Some(id) => id,
+            None => return,
         };
+        self.critical_path.push(critical_path_task);
+
+        // follow the first dependency that determines exactly when the current critical path task starts, or else the
+        // first task it waited for to release its resources
+        while let Some(dependency) = self.binding_dependency(scheduled_task(critical_path_task), position_in_schedule)
+            .or_else(|| self.resource_dependencies(critical_path_task).first().copied()) {
+            critical_path_task = dependency;
+            self.critical_path.push(dependency);
+            if scheduled_task(dependency).start_time == 0 {
+                break;
+            }
+        }
     }
 
-    fn set_number_of_unscheduled_tasks(&mut self) {
-        self.number_of_unscheduled_tasks = self.unscheduled_tasks.len();
+    // Whether the dependency (by position in the schedule) determines exactly when the task starts, e.g. for
+    // finish-to-start without lag: the task starts right when the dependency ends.
+    fn is_binding(&self, task: &Task, dependency: &Dependency, dependency_position: usize) -> bool {
+        let dependency_task = &self.scheduled_tasks[dependency_position];
+        dependency.earliest_start(dependency_task.start_time, dependency_task.end_time, task.duration) == task.start_time as isize
     }
 
-    fn calculate_parallelism(&mut self) {
-        let scheduled_tasks_time_nodes: Vec<_> = self.scheduled_tasks_time_nodes.iter().rev().collect();
+    // the tasks that kept the task waiting for resources, none when the capacities did not delay it
+    fn resource_dependencies(&self, id: TaskId) -> &[TaskId] {
+        self.resource_dependencies.get(id.index()).map_or(&[], Vec::as_slice)
+    }
 
-        for node in scheduled_tasks_time_nodes.windows(2) {
-            let mut counter: usize = 0;
+    fn binding_dependency(&self, task: &Task, position_in_schedule: &[usize]) -> Option<TaskId> {
+        task.dependencies
+            .iter()
+            .filter_map(|dependency| Some((dependency, dependency.id?)))
+            .find(|(dependency, id)| self.is_binding(task, dependency, position_in_schedule[id.index()]))
+            .map(|(_, id)| id)
+    }
 
-            // loop through reversed schedule: last tasks to first tasks
-            for task in self.scheduled_tasks.iter().rev() {
-                if self.do_overlap(*node[1], *node[0], task.start_time, task.end_time) {
-                    counter += 1;
-                };
-                if counter > self.max_parallelism {
-                    self.max_parallelism = counter;
-                };
+    // A dependency is a critical edge when both tasks have no float and the dependency determines when the task starts,
+    // and so is a resource dependency between tasks without float. Returned as pairs of positions in the schedule:
+    // (dependency, dependent).
+    pub fn critical_edges(&self) -> Vec<(usize, usize)> {
+        let position_in_schedule = self.graph.topological_positions();
+        let mut edges = vec![];
+
+        for (position, &id) in self.graph.topological_order.iter().enumerate() {
+            let task = &self.scheduled_tasks[position];
+            if self.timings[position].total_float > 0 {
+                continue;
+            }
+            for (dependency, dependency_id) in task.dependencies.iter().filter_map(|dependency| Some((dependency, dependency.id?))) {
+                let dependency_position = position_in_schedule[dependency_id.index()];
+                if self.timings[dependency_position].total_float == 0 && self.is_binding(task, dependency, dependency_position) {
+                    edges.push((dependency_position, position));
+                }
+            }
+            for &dependency_id in self.resource_dependencies(id) {
+                let dependency_position = position_in_schedule[dependency_id.index()];
+                if self.timings[dependency_position].total_float == 0 {
+                    edges.push((dependency_position, position));
+                }
             }
         }
-    }
 
-    fn do_overlap(&self, a_start: TimeMoment, a_end: TimeMoment, b_start: TimeMoment, b_end: TimeMoment) -> bool {
-        a_start < b_end && b_start < a_end
+        edges.sort_unstable();
+        edges
     }
 
-    fn assemble_critical_path_tasks(&mut self) {
-        self.critical_path.push(self.last_task.name);
-        let mut dependencies: Vec<String> = self.last_task.dependencies.clone();
-        let mut critical_path_task_start_time: TimeMoment = self.last_task.start_time;
-        let mut critical_path_task: Task = Default::default();
-        let mut finished = false;
-
-        while !finished && !dependencies.is_empty() {
-            'dependencies: for dependency in dependencies {
-
-                // to track the critical path loop through reversed schedule: from last task to first task
-                for task in self.scheduled_tasks.iter().rev() {
-                    if task.name == dependency && task.end_time == critical_path_task_start_time {
-                        self.critical_path.push(task.name);
-                        critical_path_task_start_time = task.start_time;
-                        critical_path_task = task.clone();
-                        if task.start_time == 0 {
-                            finished = true;
-                        }
-                        break 'dependencies;
+    // every chain of critical edges from a task starting at 0 to a task ending at the minimum duration of the job, and
+    // whether the listing stopped at MAX_CRITICAL_PATHS with more chains left
+    pub fn critical_paths(&self) -> (Vec<Vec<&str>>, bool) {
+        let mut critical_dependents: Vec<Vec<usize>> = vec![vec![]; self.scheduled_tasks.len()];
+        let mut has_critical_dependency = vec![false; self.scheduled_tasks.len()];
+        for (dependency, dependent) in self.critical_edges() {
+            critical_dependents[dependency].push(dependent);
+            has_critical_dependency[dependent] = true;
+        }
+
+        let mut paths = vec![];
+        for (start, timing) in self.timings.iter().enumerate() {
+            if timing.total_float > 0 || has_critical_dependency[start] {
+                continue;
+            }
+
+            // depth first, with for each task on the current path the next dependent to visit
+            let mut path: Vec<(usize, usize)> = vec![(start, 0)];
+            while let Some(&mut (position, ref mut next)) = path.last_mut() {
+                if critical_dependents[position].is_empty() {
+                    if paths.len() == MAX_CRITICAL_PATHS {
+                        return (paths, true);
                     }
+                    paths.push(path.iter().map(|&(position, _)| self.scheduled_tasks[position].name.as_str()).collect());
+                    path.pop();
+                } else if *next < critical_dependents[position].len() {
+                    let dependent = critical_dependents[position][*next];
+                    *next += 1;
+                    path.push((dependent, 0));
+                } else {
+                    path.pop();
                 }
             }
-            dependencies = critical_path_task.dependencies.clone();
         }
+
+        (paths, false)
     }
 
     pub fn print_output(&self) -> String {
-        format!(r#"Critical: {}
+        let critical = if self.output_options.all_critical_paths {
+            let (paths, truncated) = self.critical_paths();
+            let mut lines: Vec<String> = paths.iter().map(|path| format!("Critical: {}", path.join("->"))).collect();
+            if truncated {
+                lines.push(format!("Critical: ... (stopped after {} paths)", MAX_CRITICAL_PATHS));
+            }
+            lines.join("\n")
+        } else {
+            format!("Critical: {}", self.print_critical_path(self.critical_path_names()))
+        };
+
+        let metadata = self.metadata.print();
+        let critical = if metadata.is_empty() { critical } else { format!("{}\n{}", metadata, critical) };
+        let output = format!(r#"{}
 Minimum: {}
-Parallelism: {}"#, self.print_critical_path(self.critical_path.clone()), self.last_task.end_time, self.max_parallelism)
+Parallelism: {}"#, critical, self.time_scale.format(self.last_task.end_time), self.max_parallelism);
+
+        let peak_usage = self.resources.print_peak_usage();
+        let output = if peak_usage.is_empty() { output } else { format!("{}\nResources: {}", output, peak_usage) };
+
+        let mut sections = vec![output];
+        if self.output_options.timings {
+            sections.push(self.print_timings());
+        }
+        if self.output_options.profile {
+            sections.push(self.print_parallelism_profile());
+        }
+        if self.output_options.gantt {
+            sections.push(print_ascii_gantt(self));
+        }
+
+        sections.join("\n\n")
+    }
+
+    pub fn print_json_output(&self) -> String {
+        let (critical_paths, critical_paths_truncated) = if self.output_options.all_critical_paths {
+            let (paths, truncated) = self.critical_paths();
+            (Some(paths), truncated)
+        } else {
+            (None, false)
+        };
+        to_json(&ScheduleOutput {
+            metadata: build_metadata_output(&self.metadata),
+            tasks: self.scheduled_tasks
+                .iter()
+                .zip(&self.timings)
+                .map(|(task, timing)| ScheduledTaskOutput {
+                    name: &task.name,
+                    duration: task.duration,
+                    start_time: task.start_time,
+                    end_time: task.end_time,
+                    slack: timing.total_float,
+                    earliest_start: timing.earliest_start,
+                    earliest_finish: timing.earliest_finish,
+                    latest_start: timing.latest_start,
+                    latest_finish: timing.latest_finish,
+                    total_float: timing.total_float,
+                    free_float: timing.free_float,
+                    dependencies: task.dependencies.iter().map(|dependency| dependency.print(self.time_scale)).collect(),
+                })
+                .collect(),
+            critical_path: self.critical_path_names().into_iter().rev().collect(),
+            critical_paths,
+            critical_paths_truncated,
+            critical_edges: self.output_options.all_critical_paths.then(|| self.critical_edges()
+                .into_iter()
+                .map(|(dependency, dependent)| (self.scheduled_tasks[dependency].name.as_str(), self.scheduled_tasks[dependent].name.as_str()))
+                .collect()),
+            makespan: self.last_task.end_time,
+            tick: self.time_scale.tick(),
+            max_parallelism: self.max_parallelism,
+            parallelism_profile: self.parallelism_profile
+                .iter()
+                .map(|interval| ParallelismIntervalOutput { from: interval.from, to: interval.to, tasks: interval.tasks })
+                .collect(),
+            resources: self.resources.to_output(),
+        })
+    }
+
+    pub fn print_timings(&self) -> String {
+        let header = ["Task", "ES", "EF", "LS", "LF", "Total float", "Free float"].map(String::from).to_vec();
+        let rows = self.scheduled_tasks
+            .iter()
+            .zip(&self.timings)
+            .map(|(task, timing)| vec![
+                task.name.to_string(),
+                self.time_scale.format(timing.earliest_start),
+                self.time_scale.format(timing.earliest_finish),
+                self.time_scale.format(timing.latest_start),
+                self.time_scale.format(timing.latest_finish),
+                self.time_scale.format(timing.total_float),
+                self.time_scale.format(timing.free_float),
+            ]);
+
+        print_table(once(header).chain(rows).collect())
+    }
+
+    pub fn print_parallelism_profile(&self) -> String {
+        let header = ["From", "To", "Tasks"].map(String::from).to_vec();
+        let rows = self.parallelism_profile
+            .iter()
+            .map(|interval| vec![self.time_scale.format(interval.from), self.time_scale.format(interval.to), interval.tasks.to_string()]);
+
+        print_table(once(header).chain(rows).collect())
+    }
+
+    pub fn to_schedule(&self) -> Schedule {
+        Schedule {
+            tasks: self.scheduled_tasks
+                .iter()
+                .zip(&self.timings)
+                .map(|(task, &timing)| ScheduledTask {
+                    name: task.name.to_string(),
+                    duration: task.duration,
+                    start_time: task.start_time,
+                    end_time: task.end_time,
+                    timing,
+                })
+                .collect(),
+            critical_path: self.critical_path_names().into_iter().rev().map(str::to_string).collect(),
+            makespan: self.last_task.end_time,
+            max_parallelism: self.max_parallelism,
+            parallelism_profile: self.parallelism_profile.clone(),
+            time_scale: self.time_scale,
+        }
+    }
+
+    // from the last task of the critical path back to the first
+    pub fn critical_path_names(&self) -> Vec<&str> {
+        self.critical_path.iter().map(|&id| self.graph.names.name(id)).collect()
     }
 
     fn print_critical_path(&self, input: Vec<&str>) -> String {
@@ -181,24 +579,64 @@ Parallelism: {}"#, self.print_critical_path(self.critical_path.clone()), self.la
     }
 }
 
+// Schedules a parsed job, or one made in code, within the capacities of its resources. A job with errors is not
+// scheduled, and every error is returned as the binary reports them.
+pub fn schedule_job(job: Job) -> Result<Schedule, JobError> {
+    let errors = check_job(&job);
+    if !errors.is_empty() {
+        return Err(JobError { errors });
+    }
+    let mut scheduler = build_job_scheduler(job);
+    scheduler.schedule().map_err(|error| JobError { errors: vec![error] })?;
+
+    Ok(scheduler.to_schedule())
+}
+
+// Every error of the job: the violations of the job consistency rules, capacities declared more than once and tasks
+// that need more of a resource than its capacity.
+pub fn check_job(job: &Job) -> Vec<ScheduleError> {
+    let mut errors = check_tasks(&job.tasks);
+    errors.extend(check_declared_capacities(job));
+    errors.extend(build_resource_pool(&job.capacities).capacity_errors(&job.tasks));
+    errors
+}
+
+// A scheduler for the tasks of a job with the capacities of its resources, its metadata and the length of its tick,
+// as the times of its tasks count in those ticks.
+pub fn build_job_scheduler(job: Job) -> Scheduler {
+    let mut scheduler = build_scheduler(job.tasks);
+    scheduler.resources = build_resource_pool(&job.capacities);
+    scheduler.time_scale = job.time_scale;
+    scheduler.metadata = job.metadata;
+    scheduler
+}
+
 pub fn build_scheduler(unscheduled_tasks: Vec<Task>) -> Scheduler {
-    let mut scheduler = Scheduler {
-        number_of_unscheduled_tasks: 0,
+    Scheduler {
         unscheduled_tasks,
+        graph: Default::default(),
         scheduled_tasks: vec![],
+        timings: vec![],
         critical_path: vec![],
+        resource_dependencies: vec![],
         last_task: Default::default(),
         scheduled_tasks_time_nodes: BTreeSet::from([0]),
+        parallelism_profile: vec![],
         max_parallelism: 0,
-    };
-
-    scheduler.initialize();
-    scheduler
+        resources: Default::default(),
+        policy: Default::default(),
+        time_scale: Default::default(),
+        metadata: Default::default(),
+        output_options: Default::default(),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::MergeError;
+    use crate::input::parsers::parse_job;
+    use crate::task::SourceLocation;
 
     #[test]
     fn run() {
@@ -209,7 +647,7 @@ mod tests {
         let tasks = vec![a, b, c];
 
         let scheduler = build_scheduler(tasks);
-        let schedule_output = scheduler.run();
+        let schedule_output = scheduler.run().unwrap();
 
         assert_eq!(schedule_output, r#"Critical: A->B
 Minimum: 2
@@ -217,66 +655,384 @@ Parallelism: 2"#)
     }
 
     #[test]
-    #[should_panic(expected = "Progress stopped while scheduling: the input file contains logical inconsistencies, like non-existent dependencies, or no tasks without dependencies, etc.")]
-    fn run_only_tasks_with_dependencies() {
-        let a = Task::new("A", 1, vec!["B".to_string()]);
+    fn run_with_json_format() {
+        let a = Task::new("A", 1, vec![]);
         let b = Task::new("B", 1, vec!["A".to_string()]);
+        let c = Task::new("C", 3, vec!["A".to_string()]);
+
+        let scheduler = build_scheduler(vec![a, b, c]);
+        let output: serde_json::Value = serde_json::from_str(&scheduler.run_with_format(Format::Json).unwrap()).unwrap();
+
+        assert_eq!(output, serde_json::json!({
+            "tasks": [
+                {"name": "A", "duration": 1, "start_time": 0, "end_time": 1, "slack": 0, "earliest_start": 0, "earliest_finish": 1,
+                 "latest_start": 0, "latest_finish": 1, "total_float": 0, "free_float": 0, "dependencies": []},
+                {"name": "B", "duration": 1, "start_time": 1, "end_time": 2, "slack": 2, "earliest_start": 1, "earliest_finish": 2,
+                 "latest_start": 3, "latest_finish": 4, "total_float": 2, "free_float": 2, "dependencies": ["A"]},
+                {"name": "C", "duration": 3, "start_time": 1, "end_time": 4, "slack": 0, "earliest_start": 1, "earliest_finish": 4,
+                 "latest_start": 1, "latest_finish": 4, "total_float": 0, "free_float": 0, "dependencies": ["A"]}
+            ],
+            "critical_path": ["A", "C"],
+            "makespan": 4,
+            "max_parallelism": 2,
+            "parallelism_profile": [
+                {"from": 0, "to": 1, "tasks": 1},
+                {"from": 1, "to": 2, "tasks": 2},
+                {"from": 2, "to": 4, "tasks": 1}
+            ]
+        }));
+    }
 
-        let tasks = vec![a, b];
+    #[test]
+    fn calculate_timings() {
+        let a = Task::new("A", 3, vec![]);
+        let b = Task::new("B", 1, vec![]);
+        let c = Task::new("C", 1, vec!["B".to_string()]);
+        let d = Task::new("D", 1, vec!["A".to_string()]);
+        let e = Task::new("E", 1, vec!["C".to_string()]);
+
+        let mut scheduler = build_scheduler(vec![a, b, c, d, e]);
+        scheduler.schedule_tasks().unwrap();
+        scheduler.calculate_timings();
+
+        assert_eq!(scheduler.timings, vec![
+            TaskTiming { earliest_start: 0, earliest_finish: 3, latest_start: 0, latest_finish: 3, total_float: 0, free_float: 0 },
+            TaskTiming { earliest_start: 0, earliest_finish: 1, latest_start: 1, latest_finish: 2, total_float: 1, free_float: 0 },
+            TaskTiming { earliest_start: 1, earliest_finish: 2, latest_start: 2, latest_finish: 3, total_float: 1, free_float: 0 },
+            TaskTiming { earliest_start: 3, earliest_finish: 4, latest_start: 3, latest_finish: 4, total_float: 0, free_float: 0 },
+            TaskTiming { earliest_start: 2, earliest_finish: 3, latest_start: 3, latest_finish: 4, total_float: 1, free_float: 1 },
+        ]);
+    }
 
-        let scheduler = build_scheduler(tasks);
-        scheduler.run();
+    #[test]
+    fn schedule_with_dependency_types_and_lags() {
+        let a = Task::new("A", 4, vec![]);
+        let b = Task::new("B", 3, vec![]).after(vec![Dependency { name: "A".to_string(), kind: DependencyKind::StartToStart, lag: 1, ..Default::default() }]);
+        let c = Task::new("C", 3, vec![]).after(vec![Dependency { name: "B".to_string(), kind: DependencyKind::FinishToFinish, lag: 1, ..Default::default() }]);
+        let d = Task::new("D", 1, vec![]).after(vec![Dependency { name: "A".to_string(), kind: DependencyKind::FinishToStart, lag: -1, ..Default::default() }]);
+
+        let mut scheduler = build_scheduler(vec![a, b, c, d]);
+        scheduler.schedule().unwrap();
+
+        let times: Vec<(&str, TimeMoment, TimeMoment)> = scheduler.scheduled_tasks.iter().map(|task| (task.name.as_str(), task.start_time, task.end_time)).collect();
+        assert_eq!(times, vec![("A", 0, 4), ("B", 1, 4), ("C", 2, 5), ("D", 3, 4)]);
+        assert_eq!(scheduler.critical_path_names(), vec!["C", "B", "A"]);
+        assert_eq!(scheduler.critical_edges(), vec![(0, 1), (1, 2)]);
+        assert_eq!(scheduler.timings[0], TaskTiming { earliest_start: 0, earliest_finish: 4, latest_start: 0, latest_finish: 4, total_float: 0, free_float: 0 });
+        assert_eq!(scheduler.timings[3], TaskTiming { earliest_start: 3, earliest_finish: 4, latest_start: 4, latest_finish: 5, total_float: 1, free_float: 1 });
+        assert_eq!(scheduler.max_parallelism, 4);
     }
 
     #[test]
-    #[should_panic(expected = "Progress stopped while scheduling: the input file contains logical inconsistencies, like non-existent dependencies, or no tasks without dependencies, etc.")]
-    fn run_with_non_existing_dependencies() {
-        let a = Task::new("A", 1, vec![]);
-        let b = Task::new("B", 1, vec!["C".to_string()]);
+    fn print_timings() {
+        let a = Task::new("A", 2, vec![]);
+        let b = Task::new("Build", 1, vec![]);
 
-        let tasks = vec![a, b];
+        let mut scheduler = build_scheduler(vec![a, b]);
+        scheduler.schedule().unwrap();
 
-        let scheduler = build_scheduler(tasks);
-        scheduler.run();
+        assert_eq!(scheduler.print_timings(), r#"Task   ES  EF  LS  LF  Total float  Free float
+A      0   2   0   2   0            0
+Build  0   1   1   2   1            1"#);
+    }
+
+    #[test]
+    fn print_output_in_thousandths() {
+        let a = Task::new("A", 1500, vec![]);
+        let b = Task::new("B", 250, vec!["A".to_string()]);
+
+        let mut scheduler = build_scheduler(vec![a, b]);
+        scheduler.time_scale = TimeScale::Thousandths;
+        scheduler.output_options.timings = true;
+        scheduler.schedule().unwrap();
+
+        assert_eq!(scheduler.print_output(), r#"Critical: A->B
+Minimum: 1.75
+Parallelism: 1
+
+Task  ES   EF    LS   LF    Total float  Free float
+A     0    1.5   0    1.5   0            0
+B     1.5  1.75  1.5  1.75  0            0"#);
+        assert!(scheduler.print_json_output().contains(r#""tick": "0.001""#));
+    }
+
+    #[test]
+    fn schedule_job_reports_every_error() {
+        let job = Job::default()
+            .with_capacity("gpu", 1)
+            .with_task(Task::new("A", 1, vec!["A".to_string()]))
+            .with_task(Task::new("B", 1, vec!["C".to_string()]).using(vec![("gpu".to_string(), 2)]));
+
+        let errors: Vec<String> = schedule_job(job).unwrap_err().errors.iter().map(|error| error.to_string()).collect();
+        assert_eq!(errors, vec![
+            "task A depends on itself",
+            "task B depends on unknown task C",
+            "task B uses 2 gpu but the capacity is 1",
+        ]);
+    }
+
+    #[test]
+    fn schedule_job_made_in_code() {
+        let job = Job::default()
+            .with_capacity("gpu", 1)
+            .with_task(Task::new("A", 2, vec![]).using(vec![("gpu".to_string(), 1)]))
+            .with_task(Task::new("B", 3, vec![]).using(vec![("gpu".to_string(), 1)]))
+            .with_task(Task::new("C", 1, vec!["A".to_string()]));
+
+        let schedule = schedule_job(job).unwrap();
+
+        assert_eq!(schedule.makespan, 5);
+        // B waits for A to release the gpu, so delaying A delays the job
+        assert_eq!(schedule.critical_path, vec!["A", "B"]);
+        assert_eq!(schedule.max_parallelism, 2);
+        let b = schedule.task("B").unwrap();
+        assert_eq!((b.start_time, b.end_time, b.timing.total_float), (2, 5, 0));
+        let a = schedule.task("A").unwrap();
+        assert_eq!((a.timing.latest_finish, a.timing.total_float, a.timing.free_float), (2, 0, 0));
+        assert_eq!(schedule.task("C").unwrap().timing.total_float, 2);
+    }
+
+    #[test]
+    fn schedule_parsed_job() {
+        let input = "A(1.5)\nB(1) after [A]\n".to_string();
+        let schedule = schedule_job(parse_job(&input).unwrap()).unwrap();
+        drop(input);
+
+        assert_eq!(schedule.makespan, 2500);
+        assert_eq!(schedule.time_scale, TimeScale::Thousandths);
+        assert_eq!(schedule.critical_path, vec!["A", "B"]);
+    }
+
+    #[test]
+    fn send_and_serialise_schedule() {
+        let input = "A(1.5)\nB(1) after [A]\n".to_string();
+        let job = parse_job(&input).unwrap();
+        drop(input);
+
+        let schedule = std::thread::spawn(move || schedule_job(job).unwrap()).join().unwrap();
+        let json = serde_json::to_string(&schedule).unwrap();
+        assert_eq!(serde_json::from_str::<Schedule>(&json).unwrap(), schedule);
+    }
+
+    #[test]
+    fn merge_schedules() {
+        let mut schedule = schedule_job(parse_job("A(1)\nB(1) after [A]\n").unwrap()).unwrap();
+        let other = schedule_job(parse_job("C(0.5)\nD(1)\n").unwrap()).unwrap();
+        schedule.merge(other).unwrap();
+
+        assert_eq!(schedule.time_scale, TimeScale::Thousandths);
+        assert_eq!(schedule.makespan, 2000);
+        assert_eq!(schedule.critical_path, vec!["A", "B"]);
+        assert_eq!(schedule.task("B").unwrap().start_time, 1000);
+        assert_eq!(schedule.tasks.len(), 4);
+        assert_eq!(schedule.max_parallelism, 3);
+        let profile: Vec<(TimeMoment, TimeMoment, usize)> = schedule.parallelism_profile
+            .iter()
+            .map(|interval| (interval.from, interval.to, interval.tasks))
+            .collect();
+        assert_eq!(profile, vec![(0, 500, 3), (500, 1000, 2), (1000, 2000, 1)]);
+    }
+
+    // a task of one time unit in every pair of time scales: one tick in units, a thousand in thousandths and milliseconds
+    fn merge_one_unit_tasks(first: &str, second: &str) -> Schedule {
+        let mut schedule = schedule_job(parse_job(first).unwrap()).unwrap();
+        schedule.merge(schedule_job(parse_job(second).unwrap()).unwrap()).unwrap();
+        schedule
+    }
+
+    #[test]
+    fn merge_units_and_thousandths() {
+        let schedule = merge_one_unit_tasks("A(1)\n", "B(1.0)\nC(0.5)\n");
+        assert_eq!((schedule.time_scale, schedule.makespan, schedule.task("A").unwrap().end_time), (TimeScale::Thousandths, 1000, 1000));
+        let schedule = merge_one_unit_tasks("B(1.0)\nC(0.5)\n", "A(1)\n");
+        assert_eq!((schedule.time_scale, schedule.makespan, schedule.task("A").unwrap().end_time), (TimeScale::Thousandths, 1000, 1000));
+    }
+
+    #[test]
+    fn merge_units_and_milliseconds() {
+        let schedule = merge_one_unit_tasks("A(1)\n", "B(1s)\n");
+        assert_eq!((schedule.time_scale, schedule.makespan, schedule.task("A").unwrap().end_time), (TimeScale::Milliseconds, 1000, 1000));
+        let schedule = merge_one_unit_tasks("B(1s)\n", "A(2)\n");
+        assert_eq!((schedule.time_scale, schedule.makespan, schedule.task("A").unwrap().end_time), (TimeScale::Milliseconds, 2000, 2000));
+    }
+
+    #[test]
+    fn merge_thousandths_and_milliseconds() {
+        let schedule = merge_one_unit_tasks("A(1.5)\n", "B(1s)\n");
+        assert_eq!((schedule.time_scale, schedule.makespan, schedule.task("A").unwrap().end_time), (TimeScale::Milliseconds, 1500, 1500));
+        let schedule = merge_one_unit_tasks("B(1s)\n", "A(1.5)\n");
+        assert_eq!((schedule.time_scale, schedule.makespan, schedule.task("B").unwrap().end_time), (TimeScale::Milliseconds, 1500, 1000));
+    }
+
+    #[test]
+    fn merge_schedules_with_the_same_task_name() {
+        let mut schedule = schedule_job(parse_job("A(1)\nB(1) after [A]\n").unwrap()).unwrap();
+        let unchanged = schedule.clone();
+        let other = schedule_job(parse_job("C(1)\nB(2)\n").unwrap()).unwrap();
+
+        assert_eq!(schedule.merge(other), Err(MergeError::DuplicateTaskName("B".to_string())));
+        assert_eq!(schedule, unchanged);
+    }
+
+    #[test]
+    fn merge_in_the_same_time_scale() {
+        let schedule = merge_one_unit_tasks("A(1)\n", "B(2)\n");
+        assert_eq!((schedule.time_scale, schedule.makespan), (TimeScale::Units, 2));
+        let schedule = merge_one_unit_tasks("A(1s)\n", "B(2s)\n");
+        assert_eq!((schedule.time_scale, schedule.makespan), (TimeScale::Milliseconds, 2000));
+    }
+
+    #[test]
+    fn print_output_with_metadata() {
+        let a = Task::new("A", 2, vec![]);
+
+        let mut scheduler = build_scheduler(vec![a]);
+        scheduler.metadata = Metadata { name: Some("Release".to_string()), owner: Some("Ops".to_string()), description: None };
+        scheduler.schedule().unwrap();
+
+        assert_eq!(scheduler.print_output(), r#"Job: Release
+Owner: Ops
+Critical: A
+Minimum: 2
+Parallelism: 1"#);
+        assert!(scheduler.print_json_output().starts_with(r#"{
+  "metadata": {
+    "name": "Release",
+    "owner": "Ops"
+  },"#));
     }
 
     #[test]
-    fn process_unscheduled_tasks_one_loop() {
+    fn critical_paths_with_tied_chains() {
         let a = Task::new("A", 1, vec![]);
-        let b = Task::new("B", 1, vec!["A".to_string()]);
-        let c = Task::new("C", 1, vec!["A".to_string()]);
+        let b = Task::new("B", 2, vec!["A".to_string()]);
+        let c = Task::new("C", 2, vec!["A".to_string()]);
+        let d = Task::new("D", 1, vec!["A".to_string()]);
+        let e = Task::new("E", 1, vec!["B".to_string(), "C".to_string(), "D".to_string()]);
+        let f = Task::new("F", 4, vec![]);
 
-        let tasks = vec![a.clone(), b.clone(), c.clone()];
+        let mut scheduler = build_scheduler(vec![a, b, c, d, e, f]);
+        scheduler.schedule().unwrap();
+
+        assert_eq!(scheduler.critical_edges(), vec![(0, 1), (0, 2), (1, 4), (2, 4)]);
+        assert_eq!(scheduler.critical_paths(), (vec![vec!["A", "B", "E"], vec!["A", "C", "E"], vec!["F"]], false));
+    }
+
+    #[test]
+    fn critical_paths_stop_at_maximum() {
+        // every task depends on both tasks of the layer before it, so there are 2^10 chains
+        let mut tasks = vec![];
+        for layer in 0..10 {
+            for name in ["a", "b"] {
+                let dependencies = if layer == 0 { vec![] } else { vec![format!("{}a", layer - 1), format!("{}b", layer - 1)] };
+                tasks.push(Task::new(&format!("{}{}", layer, name), 1, dependencies));
+            }
+        }
 
         let mut scheduler = build_scheduler(tasks);
-        scheduler.process_unscheduled_tasks();
-        assert_eq!(scheduler.number_of_unscheduled_tasks, 2);
-        assert_eq!(scheduler.scheduled_tasks, [Task { name: "A", duration: 1, start_time: 0, end_time: 1, dependencies: vec![] }]);
-        assert_eq!(scheduler.last_task, Task { name: "A", duration: 1, start_time: 0, end_time: 1, dependencies: vec![] });
-        assert_eq!(scheduler.scheduled_tasks_time_nodes, BTreeSet::from([0, 1]));
+        scheduler.output_options.all_critical_paths = true;
+        scheduler.schedule().unwrap();
+
+        let (paths, truncated) = scheduler.critical_paths();
+        assert_eq!((paths.len(), truncated), (MAX_CRITICAL_PATHS, true));
+        assert!(scheduler.print_output().contains("\nCritical: ... (stopped after 1000 paths)\nMinimum: 10"));
+        assert!(scheduler.print_json_output().contains(r#""critical_paths_truncated": true"#));
     }
 
     #[test]
-    fn process_unscheduled_tasks_two_loops() {
+    fn print_output_with_all_critical_paths() {
         let a = Task::new("A", 1, vec![]);
         let b = Task::new("B", 1, vec!["A".to_string()]);
         let c = Task::new("C", 1, vec!["A".to_string()]);
 
-        let tasks = vec![a.clone(), b.clone(), c.clone()];
+        let mut scheduler = build_scheduler(vec![a, b, c]);
+        scheduler.output_options.all_critical_paths = true;
 
-        let mut scheduler = build_scheduler(tasks);
-        scheduler.process_unscheduled_tasks();
-        scheduler.process_unscheduled_tasks();
-        assert_eq!(scheduler.number_of_unscheduled_tasks, 1);
-        assert_eq!(scheduler.scheduled_tasks, [
-            Task { name: "A", duration: 1, start_time: 0, end_time: 1, dependencies: vec![] },
-            Task { name: "B", duration: 1, start_time: 1, end_time: 2, dependencies: vec!["A".to_string()] }]);
-        assert_eq!(scheduler.last_task, Task { name: "B", duration: 1, start_time: 1, end_time: 2, dependencies: vec!["A".to_string()] });
-        assert_eq!(scheduler.scheduled_tasks_time_nodes, BTreeSet::from([0, 1, 2]));
+        assert_eq!(scheduler.run().unwrap(), r#"Critical: A->B
+Critical: A->C
+Minimum: 2
+Parallelism: 2"#);
+    }
+
+    #[test]
+    fn schedule_tasks_within_resource_capacities() {
+        let a = Task::new("A", 2, vec![]).using(vec![("gpu".to_string(), 1)]);
+        let b = Task::new("B", 1, vec![]).using(vec![("gpu".to_string(), 2)]);
+        let c = Task::new("C", 1, vec![]).using(vec![("gpu".to_string(), 1), ("mem".to_string(), 4)]);
+        let d = Task::new("D", 1, vec!["A".to_string()]);
+
+        let mut scheduler = build_scheduler(vec![a, b, c, d]);
+        scheduler.resources = build_resource_pool(&[("gpu".to_string(), 2)]);
+
+        scheduler.schedule().unwrap();
+
+        // B needs both gpus, so it waits for A, which ends after C
+        assert_eq!(scheduler.resource_dependencies, vec![vec![], vec![TaskId(0)], vec![], vec![]]);
+        assert_eq!(scheduler.critical_edges(), vec![(0, 1), (0, 3)]);
+        assert_eq!(scheduler.print_output(), r#"Critical: A->B
+Minimum: 3
+Parallelism: 2
+Resources: gpu 2 of 2, mem 4"#);
+        let total_floats: Vec<Duration> = scheduler.timings.iter().map(|timing| timing.total_float).collect();
+        assert_eq!(total_floats, vec![0, 0, 2, 0]);
+    }
+
+    #[test]
+    fn schedule_tasks_within_resource_capacities_by_policy() {
+        let a = Task::new("A", 1, vec![]).using(vec![("seat".to_string(), 1)]);
+        let b = Task::new("B", 3, vec![]).using(vec![("seat".to_string(), 1)]);
+        let c = Task::new("C", 1, vec!["A".to_string()]).using(vec![("seat".to_string(), 1)]).with_priority(1);
+
+        let mut scheduler = build_scheduler(vec![a, b, c]);
+        scheduler.resources = build_resource_pool(&[("seat".to_string(), 1)]);
+        scheduler.policy = Policy::LongestProcessingTime;
+        scheduler.schedule_tasks().unwrap();
+
+        let start_times: Vec<(&str, TimeMoment)> = scheduler.scheduled_tasks.iter().map(|task| (task.name.as_str(), task.start_time)).collect();
+        assert_eq!(start_times, vec![("A", 3), ("B", 0), ("C", 4)]);
     }
 
     #[test]
-    fn process_unscheduled_tasks_three_loops() {
+    fn schedule_tasks_with_too_little_capacity() {
+        let a = Task::new("A", 2, vec![]).using(vec![("gpu".to_string(), 3)]).at(1, 1);
+
+        let mut scheduler = build_scheduler(vec![a]);
+        scheduler.resources = build_resource_pool(&[("gpu".to_string(), 2)]);
+
+        assert_eq!(scheduler.run().unwrap_err().to_string(), "task A uses 3 gpu but the capacity is 2 (line 1, column 1)");
+    }
+
+    #[test]
+    fn run_only_tasks_with_dependencies() {
+        let a = Task::new("A", 1, vec!["B".to_string()]).at(1, 1);
+        let b = Task::new("B", 1, vec!["A".to_string()]).at(2, 1);
+
+        let tasks = vec![a, b];
+
+        let scheduler = build_scheduler(tasks);
+        assert_eq!(scheduler.run(), Err(ScheduleError::Cycle {
+            tasks: vec!["A".to_string(), "B".to_string()],
+            locations: vec![Some(SourceLocation::new(1, 1)), Some(SourceLocation::new(2, 1))],
+        }));
+    }
+
+    #[test]
+    fn run_with_non_existing_dependencies() {
+        let a = Task::new("A", 1, vec![]).at(1, 1);
+        let b = Task::new("B", 1, vec!["C".to_string()]).at(2, 1);
+
+        let tasks = vec![a, b];
+
+        let scheduler = build_scheduler(tasks);
+        assert_eq!(scheduler.run(), Err(ScheduleError::UnknownDependency {
+            task: "B".to_string(),
+            dependency: "C".to_string(),
+            location: Some(SourceLocation::new(2, 1)),
+        }));
+    }
+
+    #[test]
+    fn schedule_tasks() {
         let a = Task::new("A", 1, vec![]);
         let b = Task::new("B", 1, vec!["A".to_string()]);
         let c = Task::new("C", 1, vec!["A".to_string()]);
@@ -284,42 +1040,47 @@ Parallelism: 2"#)
         let tasks = vec![a.clone(), b.clone(), c.clone()];
 
         let mut scheduler = build_scheduler(tasks);
-        scheduler.process_unscheduled_tasks();
-        scheduler.process_unscheduled_tasks();
-        scheduler.process_unscheduled_tasks();
-        assert_eq!(scheduler.number_of_unscheduled_tasks, 0);
+        scheduler.schedule_tasks().unwrap();
+        assert_eq!(scheduler.graph.topological_order, vec![TaskId(0), TaskId(1), TaskId(2)]);
         assert_eq!(scheduler.scheduled_tasks, [
-            Task { name: "A", duration: 1, start_time: 0, end_time: 1, dependencies: vec![] },
-            Task { name: "B", duration: 1, start_time: 1, end_time: 2, dependencies: vec!["A".to_string()] },
-            Task { name: "C", duration: 1, start_time: 1, end_time: 2, dependencies: vec!["A".to_string()] }]);
-        assert_eq!(scheduler.last_task, Task { name: "B", duration: 1, start_time: 1, end_time: 2, dependencies: vec!["A".to_string()] });
+            Task { name: "A".to_string(), duration: 1, start_time: 0, end_time: 1, dependencies: vec![], resources: vec![], priority: 0, estimate: None, location: None, command: None },
+            Task { name: "B".to_string(), duration: 1, start_time: 1, end_time: 2, dependencies: vec![Dependency::finish_to_start("A")], resources: vec![], priority: 0, estimate: None, location: None, command: None },
+            Task { name: "C".to_string(), duration: 1, start_time: 1, end_time: 2, dependencies: vec![Dependency::finish_to_start("A")], resources: vec![], priority: 0, estimate: None, location: None, command: None }]);
+        assert_eq!(scheduler.last_task, Task { name: "B".to_string(), duration: 1, start_time: 1, end_time: 2, dependencies: vec![Dependency::finish_to_start("A")], resources: vec![], priority: 0, estimate: None, location: None, command: None });
         assert_eq!(scheduler.scheduled_tasks_time_nodes, BTreeSet::from([0, 1, 2]));
     }
 
     #[test]
-    fn initialize() {
-        let a = Task::new("A", 1, vec![]);
+    fn schedule_tasks_listed_before_their_dependencies() {
+        let c = Task::new("C", 2, vec!["B".to_string()]);
         let b = Task::new("B", 1, vec!["A".to_string()]);
-        let c = Task::new("C", 1, vec!["A".to_string()]);
+        let a = Task::new("A", 1, vec![]);
 
-        let tasks = vec![a.clone(), b.clone(), c.clone()];
+        let tasks = vec![c.clone(), b.clone(), a.clone()];
 
         let mut scheduler = build_scheduler(tasks);
-        scheduler.initialize();
-        assert_eq!(scheduler.number_of_unscheduled_tasks, 3);
+        scheduler.schedule_tasks().unwrap();
+        assert_eq!(scheduler.graph.topological_order, vec![TaskId(2), TaskId(1), TaskId(0)]);
+        assert_eq!(scheduler.scheduled_tasks, [
+            Task { name: "A".to_string(), duration: 1, start_time: 0, end_time: 1, dependencies: vec![], resources: vec![], priority: 0, estimate: None, location: None, command: None },
+            Task { name: "B".to_string(), duration: 1, start_time: 1, end_time: 2, dependencies: vec![Dependency::finish_to_start("A")], resources: vec![], priority: 0, estimate: None, location: None, command: None },
+            Task { name: "C".to_string(), duration: 2, start_time: 2, end_time: 4, dependencies: vec![Dependency::finish_to_start("B")], resources: vec![], priority: 0, estimate: None, location: None, command: None }]);
+        assert_eq!(scheduler.last_task.name, "C");
     }
 
     #[test]
-    #[should_panic(expected="The taskname A is not unique")]
     fn taskname_is_not_unique() {
-        let a = Task::new("A", 1, vec![]);
-        let b = Task::new("A", 1, vec![]);
+        let a = Task::new("A", 1, vec![]).at(1, 1);
+        let b = Task::new("A", 1, vec![]).at(2, 1);
 
         let tasks = vec![a.clone(), b.clone()];
 
         let mut scheduler = build_scheduler(tasks);
-        scheduler.process_unscheduled_tasks();
-        scheduler.process_unscheduled_tasks();
+        assert_eq!(scheduler.schedule_tasks(), Err(ScheduleError::DuplicateTaskName {
+            name: "A".to_string(),
+            first: Some(SourceLocation::new(1, 1)),
+            duplicate: Some(SourceLocation::new(2, 1)),
+        }));
     }
 
     #[test]
@@ -331,17 +1092,34 @@ Parallelism: 2"#)
         let tasks = vec![a.clone(), b.clone(), c.clone()];
 
         let mut scheduler = build_scheduler(tasks);
-        scheduler.process_unscheduled_tasks();
-        scheduler.calculate_parallelism();
-        assert_eq!(scheduler.max_parallelism, 1);
-        scheduler.process_unscheduled_tasks();
-        scheduler.calculate_parallelism();
-        assert_eq!(scheduler.max_parallelism, 1);
-        scheduler.process_unscheduled_tasks();
+        scheduler.schedule_tasks().unwrap();
         scheduler.calculate_parallelism();
+        assert_eq!(scheduler.parallelism_profile, vec![
+            ParallelismInterval { from: 0, to: 1, tasks: 1 },
+            ParallelismInterval { from: 1, to: 2, tasks: 2 },
+        ]);
         assert_eq!(scheduler.max_parallelism, 2);
     }
 
+    #[test]
+    fn calculate_parallelism_skips_tasks_without_duration() {
+        let a = Task::new("A", 2, vec![]);
+        let b = Task::new("B", 0, vec![]);
+        let c = Task::new("C", 3, vec!["B".to_string()]);
+        let d = Task::new("D", 1, vec!["A".to_string()]);
+
+        let mut scheduler = build_scheduler(vec![a, b, c, d]);
+        scheduler.schedule().unwrap();
+
+        assert_eq!(scheduler.parallelism_profile, vec![
+            ParallelismInterval { from: 0, to: 2, tasks: 2 },
+            ParallelismInterval { from: 2, to: 3, tasks: 2 },
+        ]);
+        assert_eq!(scheduler.print_parallelism_profile(), r#"From  To  Tasks
+0     2   2
+2     3   2"#);
+    }
+
     #[test]
     fn assemble_critical_path_when_only_tasks_without_dependencies() {
         let a = Task::new("A", 1, vec![]);
@@ -351,13 +1129,11 @@ Parallelism: 2"#)
         let tasks = vec![a.clone(), b.clone(), c.clone()];
 
         let mut scheduler = build_scheduler(tasks);
-        scheduler.process_unscheduled_tasks();
-        scheduler.process_unscheduled_tasks();
-        scheduler.process_unscheduled_tasks();
+        scheduler.schedule_tasks().unwrap();
 
         scheduler.assemble_critical_path_tasks();
 
-        assert_eq!(scheduler.critical_path, vec!["B"]);
+        assert_eq!(scheduler.critical_path_names(), vec!["B"]);
     }
 
     #[test]
@@ -369,12 +1145,10 @@ Parallelism: 2"#)
         let tasks = vec![a.clone(), b.clone(), c.clone()];
 
         let mut scheduler = build_scheduler(tasks);
-        scheduler.process_unscheduled_tasks();
-        scheduler.process_unscheduled_tasks();
-        scheduler.process_unscheduled_tasks();
+        scheduler.schedule_tasks().unwrap();
 
         scheduler.assemble_critical_path_tasks();
 
-        assert_eq!(scheduler.critical_path, vec!["C", "A"]);
+        assert_eq!(scheduler.critical_path_names(), vec!["C", "A"]);
     }
 }
\ No newline at end of file
@@ -0,0 +1,345 @@
+use std::f64::consts::PI;
+use std::iter::once;
+use std::str::FromStr;
+use anyhow::{bail, Error};
+use crate::error::ScheduleError;
+use crate::output::{
+    build_metadata_output,
+    print_table,
+    to_json,
+    CriticalityOutput,
+    Format,
+    PercentileOutput,
+    SimulationOutput,
+};
+use crate::scheduler::Scheduler;
+use crate::task::{
+    Duration,
+    Estimate,
+    TimeMoment,
+};
+use crate::time::TimeScale;
+
+
+const PERCENTILES: [usize; 3] = [50, 90, 99];
+
+// How the duration of a task with a three-point estimate is sampled. PERT is a beta distribution that weighs
+// the likely duration four times as heavy as the triangular distribution does.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Distribution {
+    #[default]
+    Pert,
+    Triangular,
+}
+
+impl FromStr for Distribution {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "pert" => Ok(Distribution::Pert),
+            "triangular" => Ok(Distribution::Triangular),
+            _ => bail!("Unknown distribution {}, should be pert or triangular", input),
+        }
+    }
+}
+
+impl Distribution {
+    fn name(self) -> &'static str {
+        match self {
+            Distribution::Pert => "pert",
+            Distribution::Triangular => "triangular",
+        }
+    }
+
+    fn sample(self, estimate: &Estimate, random: &mut Random) -> Duration {
+        let (optimistic, likely, pessimistic) = (estimate.optimistic as f64, estimate.likely as f64, estimate.pessimistic as f64);
+        let range = pessimistic - optimistic;
+        if range == 0.0 {
+            return estimate.likely;
+        }
+
+        let duration = match self {
+            Distribution::Pert => {
+                let alpha = 1.0 + 4.0 * (likely - optimistic) / range;
+                let beta = 1.0 + 4.0 * (pessimistic - likely) / range;
+                optimistic + range * random.next_beta(alpha, beta)
+            }
+            // the inverse of the cumulative distribution function
+            Distribution::Triangular => {
+                let uniform = random.next_f64();
+                let likely_fraction = (likely - optimistic) / range;
+                if uniform < likely_fraction {
+                    optimistic + (uniform * range * (likely - optimistic)).sqrt()
+                } else {
+                    pessimistic - ((1.0 - uniform) * range * (pessimistic - likely)).sqrt()
+                }
+            }
+        };
+        duration.round() as Duration
+    }
+}
+
+// SplitMix64, a small generator that gives the same numbers for the same seed on every platform.
+#[derive(Debug, Clone)]
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // standard normal by the Box-Muller transform
+    fn next_normal(&mut self) -> f64 {
+        let (u1, u2) = (1.0 - self.next_f64(), self.next_f64());
+        (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+    }
+
+    // Marsaglia and Tsang's method, for a shape of at least 1 which PERT always has
+    fn next_gamma(&mut self, shape: f64) -> f64 {
+        let d = shape - 1.0 / 3.0;
+        let c = 1.0 / (9.0 * d).sqrt();
+        loop {
+            let x = self.next_normal();
+            let v = (1.0 + c * x).powi(3);
+            if v > 0.0 && (1.0 - self.next_f64()).ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+                return d * v;
+            }
+        }
+    }
+
+    fn next_beta(&mut self, alpha: f64, beta: f64) -> f64 {
+        let x = self.next_gamma(alpha);
+        let y = self.next_gamma(beta);
+        x / (x + y)
+    }
+}
+
+// Schedules the job of the scheduler a number of runs with the durations of the tasks with a three-point estimate
+// sampled from a seeded generator, so the same seed gives the same results.
+#[derive(Debug, Clone)]
+pub struct Simulator {
+    pub scheduler: Scheduler,
+    pub runs: usize,
+    pub distribution: Distribution,
+    pub seed: u64,
+    // the makespan of every run, sorted
+    pub makespans: Vec<TimeMoment>,
+    // by task index, the number of runs in which the task was on a critical path
+    pub critical_runs: Vec<usize>,
+}
+
+impl Simulator {
+    pub fn run(self) -> Result<String, ScheduleError> {
+        self.run_with_format(Format::Text)
+    }
+
+    pub fn run_with_format(mut self, format: Format) -> Result<String, ScheduleError> {
+        self.simulate()?;
+        Ok(match format {
+            Format::Text => self.print_output(),
+            Format::Json => self.print_json_output(),
+        })
+    }
+
+    pub fn simulate(&mut self) -> Result<(), ScheduleError> {
+        // sampled durations are fractions, so a job in whole time units is simulated in thousandths of a unit
+        if self.scheduler.time_scale == TimeScale::Units && self.scheduler.unscheduled_tasks.iter().any(|task| task.estimate.is_some()) {
+            for task in self.scheduler.unscheduled_tasks.iter_mut() {
+                task.rescale(1000, 1);
+            }
+            self.scheduler.time_scale = TimeScale::Thousandths;
+        }
+        let mut random = Random { state: self.seed };
+        self.makespans.clear();
+        self.critical_runs = vec![0; self.scheduler.unscheduled_tasks.len()];
+
+        for _ in 0..self.runs {
+            let mut scheduler = self.scheduler.clone();
+            for task in scheduler.unscheduled_tasks.iter_mut() {
+                if let Some(estimate) = &task.estimate {
+                    task.duration = self.distribution.sample(estimate, &mut random);
+                }
+            }
+            scheduler.schedule()?;
+
+            self.makespans.push(scheduler.last_task.end_time);
+            for (position, timing) in scheduler.timings.iter().enumerate() {
+                if timing.total_float == 0 {
+                    self.critical_runs[scheduler.graph.topological_order[position].index()] += 1;
+                }
+            }
+        }
+
+        self.makespans.sort_unstable();
+        Ok(())
+    }
+
+    // the nearest-rank percentile of the makespans
+    pub fn percentile(&self, percentile: usize) -> TimeMoment {
+        let rank = (percentile * self.makespans.len()).div_ceil(100).max(1);
+        self.makespans.get(rank - 1).copied().unwrap_or(0)
+    }
+
+    pub fn print_output(&self) -> String {
+        let time_scale = self.scheduler.time_scale;
+        let metadata = self.scheduler.metadata.print();
+        let mut lines: Vec<String> = if metadata.is_empty() { vec![] } else { vec![metadata] };
+        lines.push(format!("Runs: {} ({}, seed {})", self.runs, self.distribution.name(), self.seed));
+        lines.extend(PERCENTILES.iter().map(|&percentile| format!("P{}: {}", percentile, time_scale.format(self.percentile(percentile)))));
+
+        let header = ["Task", "Critical"].map(String::from).to_vec();
+        let rows = self.scheduler.unscheduled_tasks
+            .iter()
+            .zip(&self.critical_runs)
+            .map(|(task, &critical_runs)| vec![
+                task.name.to_string(),
+                format!("{:.1}%", 100.0 * critical_runs as f64 / self.runs.max(1) as f64),
+            ]);
+
+        format!("{}\n\n{}", lines.join("\n"), print_table(once(header).chain(rows).collect()))
+    }
+
+    pub fn print_json_output(&self) -> String {
+        to_json(&SimulationOutput {
+            metadata: build_metadata_output(&self.scheduler.metadata),
+            runs: self.runs,
+            distribution: self.distribution.name(),
+            seed: self.seed,
+            tick: self.scheduler.time_scale.tick(),
+            makespan_percentiles: PERCENTILES
+                .iter()
+                .map(|&percentile| PercentileOutput { percentile, makespan: self.percentile(percentile) })
+                .collect(),
+            criticality: self.scheduler.unscheduled_tasks
+                .iter()
+                .zip(&self.critical_runs)
+                .map(|(task, &critical_runs)| CriticalityOutput { name: &task.name, critical_runs })
+                .collect(),
+        })
+    }
+}
+
+pub fn build_simulator(scheduler: Scheduler, runs: usize) -> Simulator {
+    Simulator {
+        scheduler,
+        runs,
+        distribution: Default::default(),
+        seed: 0,
+        makespans: vec![],
+        critical_runs: vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::build_resource_pool;
+    use crate::scheduler::build_scheduler;
+    use crate::task::Task;
+
+    fn estimate(optimistic: Duration, likely: Duration, pessimistic: Duration) -> Estimate {
+        Estimate { optimistic, likely, pessimistic }
+    }
+
+    #[test]
+    fn distribution_from_str() {
+        assert_eq!("triangular".parse::<Distribution>().unwrap(), Distribution::Triangular);
+        assert!("normal".parse::<Distribution>().is_err());
+    }
+
+    #[test]
+    fn sample_within_estimate() {
+        let mut random = Random { state: 7 };
+        for distribution in [Distribution::Pert, Distribution::Triangular] {
+            for _ in 0..1000 {
+                let duration = distribution.sample(&estimate(2, 3, 8), &mut random);
+                assert!((2..=8).contains(&duration));
+            }
+            assert_eq!(distribution.sample(&estimate(4, 4, 4), &mut random), 4);
+        }
+    }
+
+    #[test]
+    fn sample_pert_around_its_mean() {
+        let mut random = Random { state: 7 };
+        let total: usize = (0..10_000).map(|_| Distribution::Pert.sample(&estimate(0, 300, 1200), &mut random)).sum();
+        // the mean of PERT is (optimistic + 4 * likely + pessimistic) / 6 = 400
+        assert!((390..=410).contains(&(total / 10_000)));
+    }
+
+    #[test]
+    fn simulate_with_the_same_seed() {
+        let a = Task::new("A", 0, vec![]).estimated(estimate(2, 3, 8));
+        let b = Task::new("B", 0, vec![]).estimated(estimate(1, 4, 6));
+        let c = Task::new("C", 1, vec!["A".to_string(), "B".to_string()]);
+
+        let mut simulator = build_simulator(build_scheduler(vec![a, b, c]), 200);
+        simulator.seed = 42;
+        simulator.simulate().unwrap();
+        let makespans = simulator.makespans.clone();
+        simulator.simulate().unwrap();
+
+        assert_eq!(simulator.makespans, makespans);
+        assert!(simulator.percentile(50) <= simulator.percentile(90) && simulator.percentile(90) <= simulator.percentile(99));
+        assert!((3000..=9000).contains(&simulator.percentile(50)));
+        assert_eq!(simulator.critical_runs[2], 200);
+        assert!(simulator.critical_runs[0] + simulator.critical_runs[1] >= 200);
+    }
+
+    #[test]
+    fn simulate_whole_time_units_in_thousandths() {
+        let a = Task::new("A", 1, vec![]).estimated(estimate(1, 1, 2));
+
+        let mut simulator = build_simulator(build_scheduler(vec![a]), 100);
+        simulator.simulate().unwrap();
+
+        // rounded to whole units nearly every run would take 1
+        assert_eq!(simulator.scheduler.time_scale, TimeScale::Thousandths);
+        assert!(simulator.makespans.iter().all(|&makespan| (1000..=2000).contains(&makespan)));
+        assert!(simulator.percentile(50) > 1000 && simulator.percentile(50) < 1500);
+        assert!(simulator.run().unwrap().starts_with("Runs: 100 (pert, seed 0)\nP50: 1."));
+    }
+
+    #[test]
+    fn simulate_tasks_waiting_for_resources() {
+        let a = Task::new("A", 0, vec![]).estimated(estimate(1, 2, 3)).using(vec![("gpu".to_string(), 1)]);
+        let b = Task::new("B", 1, vec![]).using(vec![("gpu".to_string(), 1)]);
+
+        let mut scheduler = build_scheduler(vec![a, b]);
+        scheduler.resources = build_resource_pool(&[("gpu".to_string(), 1)]);
+        let mut simulator = build_simulator(scheduler, 50);
+        simulator.simulate().unwrap();
+
+        // B waits for A, so A is as critical as B
+        assert_eq!(simulator.critical_runs, vec![50, 50]);
+    }
+
+    #[test]
+    fn print_output_without_estimates() {
+        let a = Task::new("A", 2, vec![]);
+        let b = Task::new("B", 1, vec![]);
+
+        let simulator = build_simulator(build_scheduler(vec![a, b]), 10);
+
+        assert_eq!(simulator.run().unwrap(), r#"Runs: 10 (pert, seed 0)
+P50: 2
+P90: 2
+P99: 2
+
+Task  Critical
+A     100.0%
+B     0.0%"#);
+    }
+}
@@ -0,0 +1,141 @@
+use std::fs;
+use std::io::ErrorKind;
+use serde::{Deserialize, Serialize};
+use crate::error::StateError;
+use crate::executor::TaskStatus;
+use crate::task::Task;
+
+
+// How a task ended in an execution, with the hash of its definition at the time. The timestamps are milliseconds
+// since the Unix epoch; a skipped task has not started.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskState {
+    pub name: String,
+    pub hash: String,
+    pub status: TaskStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<u64>,
+    pub finished_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+// The state file of an execution, so a rerun after an interruption or failure only runs what is left.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionState {
+    pub tasks: Vec<TaskState>,
+}
+
+impl ExecutionState {
+    pub fn task(&self, name: &str) -> Option<&TaskState> {
+        self.tasks.iter().find(|task| task.name == name)
+    }
+
+    // A task succeeded earlier when it succeeded with the same definition. Changing its command, dependencies or
+    // resources makes it run again, changing its duration, priority or the type or lag of a dependency does not as
+    // they only shape the plan.
+    pub fn succeeded_before(&self, task: &Task) -> bool {
+        self.task(&task.name)
+            .is_some_and(|state| state.status == TaskStatus::Succeeded && state.hash == definition_hash(task))
+    }
+
+    // replaces the state of the task from an earlier execution, if any
+    pub fn record(&mut self, state: TaskState) {
+        match self.tasks.iter_mut().find(|task| task.name == state.name) {
+            Some(task) => *task = state,
+            None => self.tasks.push(state),
+        }
+    }
+
+    // Writes a new file and renames it, so an interruption while writing leaves the previous state.
+    pub fn save(&self, path: &str) -> Result<(), StateError> {
+        let write_error = |error: std::io::Error| StateError::Write { file: path.to_string(), message: error.to_string() };
+        let json = serde_json::to_string_pretty(self).expect("the state only contains strings and numbers");
+        let new_path = format!("{}.new", path);
+        fs::write(&new_path, json).map_err(write_error)?;
+        fs::rename(&new_path, path).map_err(write_error)
+    }
+}
+
+// The state in the file, or an empty state when there is no file yet.
+pub fn load_state(path: &str) -> Result<ExecutionState, StateError> {
+    let read_error = |message: String| StateError::Read { file: path.to_string(), message };
+    match fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).map_err(|error| read_error(error.to_string())),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(ExecutionState::default()),
+        Err(error) => Err(read_error(error.to_string())),
+    }
+}
+
+// FNV-1a, which unlike the hasher of the standard library gives the same hash in every version of Rust, so state
+// files stay valid.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+// What a task does in exec mode. A dependency only counts by name, as exec runs every dependency finish before start.
+#[derive(Serialize)]
+struct Definition<'a> {
+    name: &'a str,
+    command: Option<&'a str>,
+    dependencies: Vec<&'a str>,
+    resources: &'a [(String, usize)],
+}
+
+// The hash of the definition as JSON, so a separator within a command can not make two definitions the same, as 16
+// hexadecimal digits.
+pub fn definition_hash(task: &Task) -> String {
+    let definition = Definition {
+        name: &task.name,
+        command: task.command.as_deref(),
+        dependencies: task.dependencies.iter().map(|dependency| dependency.name.as_str()).collect(),
+        resources: &task.resources,
+    };
+    let json = serde_json::to_string(&definition).expect("the definition only contains strings and numbers");
+
+    format!("{:016x}", fnv1a(json.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{
+        Dependency,
+        DependencyKind,
+    };
+
+    #[test]
+    fn hash_task_definitions() {
+        let task = Task::new("test", 2, vec!["lib".to_string()]).running("make test");
+        assert_eq!(definition_hash(&task), definition_hash(&task.clone()));
+        assert_eq!(definition_hash(&task), definition_hash(&Task { duration: 5, ..task.clone() }));
+        assert_ne!(definition_hash(&task), definition_hash(&task.clone().running("make check")));
+        assert_ne!(definition_hash(&task), definition_hash(&Task::new("test", 2, vec![]).running("make test")));
+        // exec runs every dependency finish before start
+        let start_to_start = Dependency { name: "lib".to_string(), kind: DependencyKind::StartToStart, lag: 1, ..Default::default() };
+        assert_eq!(definition_hash(&task), definition_hash(&task.clone().after(vec![start_to_start])));
+        // a separator in the command is part of the command
+        assert_ne!(definition_hash(&Task::new("test", 2, vec![]).running("make\nlib")),
+                   definition_hash(&Task::new("test", 2, vec!["lib".to_string()]).running("make")));
+        assert_eq!(format!("{:016x}", fnv1a(b"a")), "af63dc4c8601ec8c");
+    }
+
+    #[test]
+    fn save_and_load_state() {
+        let path = std::env::temp_dir().join(format!("schedule_tasks_state_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        assert_eq!(load_state(path).unwrap(), ExecutionState::default());
+
+        let task = Task::new("lib", 1, vec![]).running("make lib");
+        let mut state = ExecutionState::default();
+        state.record(TaskState { name: "lib".to_string(), hash: "0".to_string(), status: TaskStatus::Failed, ..Default::default() });
+        state.record(TaskState { name: "lib".to_string(), hash: definition_hash(&task), status: TaskStatus::Succeeded, ..Default::default() });
+        state.save(path).unwrap();
+
+        let state = load_state(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(state.tasks.len(), 1);
+        assert!(state.succeeded_before(&task));
+        assert!(!state.succeeded_before(&task.running("make all")));
+    }
+}
@@ -1,25 +1,379 @@
+use std::collections::{
+    BTreeMap,
+    HashSet,
+};
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::error::{
+    MergeError,
+    TimeError,
+};
+use crate::time::TimeScale;
+
 pub type Duration = usize;
 pub type TimeMoment = usize;
+pub type ResourceAmount = usize;
+pub type Priority = usize;
+// a positive lag delays the dependent task, a negative lag (a lead) lets it start earlier
+pub type Lag = isize;
+// The index of a task in the job, which the graph uses instead of its name. A type of its own, so an id is not
+// mixed up with a position in the schedule or a count.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct TaskId(pub usize);
+
+impl TaskId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyKind {
+    #[default]
+    FinishToStart,
+    StartToStart,
+    FinishToFinish,
+}
+
+#[derive(Debug, Default, Clone, Eq, Serialize, Deserialize)]
+pub struct Dependency {
+    pub name: String,
+    // the task it refers to, once the graph of the job has resolved the name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<TaskId>,
+    pub kind: DependencyKind,
+    pub lag: Lag,
+    // where the name of the task it refers to is written
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<SourceLocation>,
+}
+
+// Dependencies are the same when they refer to the same task in the same way, wherever they are written, so moving
+// a task in the job file does not change it.
+impl PartialEq for Dependency {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.kind == other.kind && self.lag == other.lag
+    }
+}
+
+// A three-point estimate of a duration, written as A(2..3..8). The task is scheduled with the likely duration,
+// a simulation samples its duration between the optimistic and the pessimistic one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Estimate {
+    pub optimistic: Duration,
+    pub likely: Duration,
+    pub pessimistic: Duration,
+}
+
+// Where a task is written in the job: its line and column, and the included file it is written in, if any.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceLocation {
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+}
 
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
-pub struct Task<'a> {
-    pub name: &'a str,
+impl SourceLocation {
+    pub fn new(line: usize, column: usize) -> Self {
+        SourceLocation { file: None, line, column }
+    }
+}
+
+// line 2, column 1, or build.tasks.in line 2, column 1
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{} ", file)?;
+        }
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Task {
+    pub name: String,
     pub duration: Duration,
     pub start_time: TimeMoment,
     pub end_time: TimeMoment,
-    pub dependencies: Vec<String>,
+    pub dependencies: Vec<Dependency>,
+    pub resources: Vec<(String, ResourceAmount)>,
+    pub priority: Priority,
+    pub estimate: Option<Estimate>,
+    pub location: Option<SourceLocation>,
+    // the shell command that runs the task in exec mode
+    pub command: Option<String>,
+}
+
+// The header lines of a job file that describe the job: name, owner and a description.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metadata {
+    pub name: Option<String>,
+    pub owner: Option<String>,
+    pub description: Option<String>,
+}
+
+impl Metadata {
+    // one line per field that is set: Job: Release 1.2, Owner: Build team, Description: Nightly build
+    pub fn print(&self) -> String {
+        [("Job", &self.name), ("Owner", &self.owner), ("Description", &self.description)]
+            .iter()
+            .filter_map(|(label, value)| value.as_ref().map(|value| format!("{}: {}", label, value)))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+// An include directive: the path of the included job file, relative to the including file, and the namespace
+// of its tasks, e.g. include "build.tasks.in" as build makes its task compile build::compile.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Include {
+    pub path: String,
+    pub namespace: Option<String>,
+    pub location: SourceLocation,
+}
+
+// A parsed job file: its metadata and the capacity per resource declared in its header, the tasks and the length of a tick.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Job {
+    pub metadata: Metadata,
+    pub capacities: Vec<(String, ResourceAmount)>,
+    // where the capacity line is written
+    pub capacity_location: Option<SourceLocation>,
+    pub includes: Vec<Include>,
+    pub tasks: Vec<Task>,
+    pub time_scale: TimeScale,
+}
+
+// Critical Path Method figures of a scheduled task. The earliest start and finish equal its start- and end-time,
+// the floats tell how much the task can slip: without delaying the job (total) or any of its dependents (free).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskTiming {
+    pub earliest_start: TimeMoment,
+    pub earliest_finish: TimeMoment,
+    pub latest_start: TimeMoment,
+    pub latest_finish: TimeMoment,
+    pub total_float: Duration,
+    pub free_float: Duration,
+}
+
+// The number of tasks running from one time node of the schedule to the next.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParallelismInterval {
+    pub from: TimeMoment,
+    pub to: TimeMoment,
+    pub tasks: usize,
+}
+
+// A task of a schedule, owning its name so the schedule can outlive the job file.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledTask {
+    pub name: String,
+    pub duration: Duration,
+    pub start_time: TimeMoment,
+    pub end_time: TimeMoment,
+    pub timing: TaskTiming,
+}
+
+// The result of scheduling a job for code that embeds the scheduler: the tasks in the order they were scheduled,
+// the critical path from its first task, the minimum duration and the parallelism, all times in ticks of the time scale.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schedule {
+    pub tasks: Vec<ScheduledTask>,
+    pub critical_path: Vec<String>,
+    pub makespan: TimeMoment,
+    pub max_parallelism: usize,
+    pub parallelism_profile: Vec<ParallelismInterval>,
+    pub time_scale: TimeScale,
+}
+
+impl Schedule {
+    pub fn task(&self, name: &str) -> Option<&ScheduledTask> {
+        self.tasks.iter().find(|task| task.name == name)
+    }
+
+    // The times in ticks of another length: multiplied by the numerator, divided by the denominator. A time too long
+    // to count in the new ticks is an error, and the schedule is left as it was.
+    pub fn rescale(&mut self, numerator: usize, denominator: usize) -> Result<(), TimeError> {
+        let rescale = |time: &mut TimeMoment| -> Result<(), TimeError> {
+            *time = time.checked_mul(numerator).ok_or(TimeError::Overflow)? / denominator;
+            Ok(())
+        };
+        let mut rescaled = self.clone();
+        for task in rescaled.tasks.iter_mut() {
+            let timing = &mut task.timing;
+            for time in [&mut task.duration, &mut task.start_time, &mut task.end_time,
+                         &mut timing.earliest_start, &mut timing.earliest_finish, &mut timing.latest_start,
+                         &mut timing.latest_finish, &mut timing.total_float, &mut timing.free_float] {
+                rescale(time)?;
+            }
+        }
+        for interval in rescaled.parallelism_profile.iter_mut() {
+            rescale(&mut interval.from)?;
+            rescale(&mut interval.to)?;
+        }
+        rescale(&mut rescaled.makespan)?;
+        *self = rescaled;
+        Ok(())
+    }
+
+    // Merges the schedule of an independent job running at the same time, as two teams would: the tasks of both,
+    // the longer critical path and the running tasks of both in each interval. Both count in the finer of their time
+    // scales: whole time units count in seconds next to finer ticks, as in a job file with included files. Task-names
+    // have to be unique across both, so a task of the merged schedule is found by its name; on an error the schedule
+    // is left as it was.
+    pub fn merge(&mut self, mut other: Schedule) -> Result<(), MergeError> {
+        let names: HashSet<&str> = self.tasks.iter().map(|task| task.name.as_str()).collect();
+        if let Some(task) = other.tasks.iter().find(|task| names.contains(task.name.as_str())) {
+            return Err(MergeError::DuplicateTaskName(task.name.clone()));
+        }
+
+        let time_scale = self.time_scale.max(other.time_scale);
+        other.rescale(time_scale.ticks_per_unit(), other.time_scale.ticks_per_unit()).map_err(MergeError::Time)?;
+        self.rescale(time_scale.ticks_per_unit(), self.time_scale.ticks_per_unit()).map_err(MergeError::Time)?;
+        self.time_scale = time_scale;
+
+        let mut changes: BTreeMap<TimeMoment, isize> = BTreeMap::new();
+        for interval in self.parallelism_profile.iter().chain(&other.parallelism_profile) {
+            *changes.entry(interval.from).or_insert(0) += interval.tasks as isize;
+            *changes.entry(interval.to).or_insert(0) -= interval.tasks as isize;
+        }
+        let time_nodes: Vec<TimeMoment> = changes.keys().copied().collect();
+        let mut running: isize = 0;
+        self.parallelism_profile = time_nodes
+            .windows(2)
+            .map(|node| {
+                running += changes[&node[0]];
+                ParallelismInterval { from: node[0], to: node[1], tasks: running as usize }
+            })
+            .collect();
+        self.max_parallelism = self.parallelism_profile.iter().map(|interval| interval.tasks).max().unwrap_or(0);
+
+        if other.makespan > self.makespan {
+            self.critical_path = other.critical_path;
+            self.makespan = other.makespan;
+        }
+        self.tasks.extend(other.tasks);
+        Ok(())
+    }
+}
+
+impl Job {
+    // for making a job in code: Job::default().with_capacity("gpu", 2).with_task(Task::new("A", 3, vec![]))
+    pub fn with_task(mut self, task: Task) -> Self {
+        self.tasks.push(task);
+        self
+    }
+
+    pub fn with_capacity(mut self, resource: &str, capacity: ResourceAmount) -> Self {
+        self.capacities.push((resource.to_string(), capacity));
+        self
+    }
 }
 
-impl<'a> Task<'a> {
-    pub fn new(name: &'a str, duration: Duration, dependencies: Vec<String>) -> Self {
+impl Dependency {
+    pub fn finish_to_start(name: &str) -> Self {
+        Dependency { name: name.to_string(), id: None, kind: DependencyKind::FinishToStart, lag: 0, location: None }
+    }
+
+    // the earliest start of a dependent task with the given duration, before clamping at 0
+    pub fn earliest_start(&self, dependency_start: TimeMoment, dependency_end: TimeMoment, duration: Duration) -> isize {
+        let earliest_start = match self.kind {
+            DependencyKind::FinishToStart => dependency_end as isize,
+            DependencyKind::StartToStart => dependency_start as isize,
+            DependencyKind::FinishToFinish => dependency_end as isize - duration as isize,
+        };
+        earliest_start + self.lag
+    }
+
+    // written as in the input file: A, A+2, start(B), finish(C)-1, D+30m
+    pub fn print(&self, time_scale: TimeScale) -> String {
+        let dependency = match self.kind {
+            DependencyKind::FinishToStart => self.name.clone(),
+            DependencyKind::StartToStart => format!("start({})", self.name),
+            DependencyKind::FinishToFinish => format!("finish({})", self.name),
+        };
+        if self.lag == 0 { dependency } else { format!("{}{}", dependency, time_scale.format_signed(self.lag)) }
+    }
+}
+
+impl Task {
+    // dependencies given by name are finish-to-start without lag
+    pub fn new(name: &str, duration: Duration, dependencies: Vec<String>) -> Self {
         Task {
-            name,
+            name: name.to_string(),
             duration,
             start_time: 0,
             end_time: 0,
-            dependencies,
+            dependencies: dependencies.iter().map(|name| Dependency::finish_to_start(name)).collect(),
+            resources: vec![],
+            priority: 0,
+            estimate: None,
+            location: None,
+            command: None,
+        }
+    }
+
+    // The earliest start allowed by the dependencies, with the start- and end-times of all tasks by task index. The
+    // dependencies have to be resolved first: one without an id would otherwise be left out and the task start too early.
+    pub fn earliest_start(&self, start_times: &[TimeMoment], end_times: &[TimeMoment]) -> TimeMoment {
+        self.dependencies
+            .iter()
+            .map(|dependency| {
+                let index = dependency.id
+                    .unwrap_or_else(|| panic!("dependency {} of task {} is not resolved", dependency.name, self.name))
+                    .index();
+                dependency.earliest_start(start_times[index], end_times[index], self.duration)
+            })
+            .max()
+            .unwrap_or(0)
+            .max(0) as TimeMoment
+    }
+
+    pub fn after(mut self, dependencies: Vec<Dependency>) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    pub fn using(mut self, resources: Vec<(String, ResourceAmount)>) -> Self {
+        self.resources = resources;
+        self
+    }
+
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn estimated(mut self, estimate: Estimate) -> Self {
+        self.duration = estimate.likely;
+        self.estimate = Some(estimate);
+        self
+    }
+
+    pub fn running(mut self, command: &str) -> Self {
+        self.command = Some(command.to_string());
+        self
+    }
+
+    // the duration, estimate and lags in ticks of another length: multiplied by the numerator, divided by the denominator
+    pub fn rescale(&mut self, numerator: usize, denominator: usize) {
+        let rescale = |time: Duration| time * numerator / denominator;
+        self.duration = rescale(self.duration);
+        if let Some(estimate) = self.estimate.as_mut() {
+            estimate.optimistic = rescale(estimate.optimistic);
+            estimate.likely = rescale(estimate.likely);
+            estimate.pessimistic = rescale(estimate.pessimistic);
+        }
+        for dependency in self.dependencies.iter_mut() {
+            dependency.lag = dependency.lag * numerator as Lag / denominator as Lag;
         }
     }
+
+    pub fn at(mut self, line: usize, column: usize) -> Self {
+        self.location = Some(SourceLocation::new(line, column));
+        self
+    }
 }
 
 #[cfg(test)]
@@ -29,6 +383,86 @@ mod tests {
     #[test]
     fn new() {
         let task = Task::new("G", 3, vec!["D".to_string(), "F".to_string()]);
-        assert_eq!(task, Task { name: "G", duration: 3, start_time: 0, end_time: 0, dependencies: vec!["D".to_string(), "F".to_string()] })
+        assert_eq!(task, Task { name: "G".to_string(), duration: 3, start_time: 0, end_time: 0, dependencies: vec![Dependency::finish_to_start("D"), Dependency::finish_to_start("F")], resources: vec![], priority: 0, estimate: None, location: None, command: None })
+    }
+
+    #[test]
+    fn at() {
+        let task = Task::new("G", 3, vec![]).at(7, 1);
+        assert_eq!(task.location, Some(SourceLocation::new(7, 1)));
+    }
+
+    #[test]
+    fn dependency_earliest_start() {
+        assert_eq!(Dependency::finish_to_start("A").earliest_start(2, 5, 1), 5);
+        assert_eq!(Dependency { name: "A".to_string(), kind: DependencyKind::StartToStart, lag: 1, ..Default::default() }.earliest_start(2, 5, 1), 3);
+        assert_eq!(Dependency { name: "A".to_string(), kind: DependencyKind::FinishToFinish, lag: -1, ..Default::default() }.earliest_start(2, 5, 1), 3);
+        assert_eq!(Dependency { name: "A".to_string(), kind: DependencyKind::FinishToStart, lag: -8, ..Default::default() }.earliest_start(2, 5, 1), -3);
+    }
+
+    #[test]
+    fn task_earliest_start() {
+        let task = Task::new("B", 1, vec![]).after(vec![Dependency { id: Some(TaskId(0)), ..Dependency::finish_to_start("A") }]);
+        assert_eq!(task.earliest_start(&[2], &[5]), 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "dependency A of task B is not resolved")]
+    fn task_earliest_start_with_unresolved_dependency() {
+        Task::new("B", 1, vec!["A".to_string()]).earliest_start(&[2], &[5]);
+    }
+
+    #[test]
+    fn print_dependency() {
+        assert_eq!(Dependency::finish_to_start("A").print(TimeScale::Units), "A");
+        assert_eq!(Dependency { name: "A".to_string(), kind: DependencyKind::FinishToStart, lag: 2, ..Default::default() }.print(TimeScale::Units), "A+2");
+        assert_eq!(Dependency { name: "B".to_string(), kind: DependencyKind::StartToStart, lag: 0, ..Default::default() }.print(TimeScale::Units), "start(B)");
+        assert_eq!(Dependency { name: "C".to_string(), kind: DependencyKind::FinishToFinish, lag: -1, ..Default::default() }.print(TimeScale::Units), "finish(C)-1");
+        assert_eq!(Dependency { name: "D".to_string(), kind: DependencyKind::FinishToStart, lag: 1_800_000, ..Default::default() }.print(TimeScale::Milliseconds), "D+30m");
+    }
+
+    #[test]
+    fn using() {
+        let task = Task::new("G", 3, vec![]).using(vec![("gpu".to_string(), 1)]);
+        assert_eq!(task.resources, vec![("gpu".to_string(), 1)]);
+    }
+
+    #[test]
+    fn print_metadata() {
+        let metadata = Metadata { name: Some("Release".to_string()), owner: None, description: Some("Every night".to_string()) };
+        assert_eq!(metadata.print(), "Job: Release\nDescription: Every night");
+        assert_eq!(Metadata::default().print(), "");
+    }
+
+    #[test]
+    fn rescale() {
+        let mut task = Task::new("G", 3000, vec![])
+            .after(vec![Dependency { name: "A".to_string(), kind: DependencyKind::FinishToStart, lag: -2000, ..Default::default() }]);
+        task.rescale(1, 1000);
+        assert_eq!(task.duration, 3);
+        assert_eq!(task.dependencies[0].lag, -2);
+    }
+
+    #[test]
+    fn rescale_schedule_beyond_the_longest_time() {
+        let mut schedule = Schedule {
+            tasks: vec![ScheduledTask { name: "A".to_string(), duration: 3, start_time: 0, end_time: 3, ..Default::default() }],
+            makespan: 3,
+            ..Default::default()
+        };
+        schedule.rescale(1000, 1).unwrap();
+        assert_eq!((schedule.tasks[0].end_time, schedule.makespan), (3000, 3000));
+
+        schedule.tasks[0].end_time = usize::MAX;
+        let unchanged = schedule.clone();
+        assert_eq!(schedule.rescale(1000, 1), Err(TimeError::Overflow));
+        assert_eq!(schedule, unchanged);
+    }
+
+    #[test]
+    fn estimated() {
+        let task = Task::new("G", 0, vec![]).estimated(Estimate { optimistic: 2, likely: 3, pessimistic: 8 });
+        assert_eq!(task.duration, 3);
+        assert_eq!(task.estimate, Some(Estimate { optimistic: 2, likely: 3, pessimistic: 8 }));
     }
 }
\ No newline at end of file
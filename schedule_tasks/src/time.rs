@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use crate::task::TimeMoment;
+
+
+// Durations and time moments are whole numbers of ticks. How long a tick is depends on how the durations
+// in the job file are written: plain integers count in time units, fractions like 1.5 in thousandths of a
+// time unit, and as soon as a duration has a unit like 90s or 1.5h every tick is a millisecond.
+// The scales are ordered from coarse to fine, so the finest of several job files can hold all their durations.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeScale {
+    #[default]
+    Units,
+    Thousandths,
+    Milliseconds,
+}
+
+pub const MILLISECONDS_PER_SECOND: TimeMoment = 1000;
+pub const MILLISECONDS_PER_MINUTE: TimeMoment = 60 * MILLISECONDS_PER_SECOND;
+pub const MILLISECONDS_PER_HOUR: TimeMoment = 60 * MILLISECONDS_PER_MINUTE;
+pub const MILLISECONDS_PER_DAY: TimeMoment = 24 * MILLISECONDS_PER_HOUR;
+
+impl TimeScale {
+    // A tick is a thousandth of a time unit both in thousandths and in milliseconds, where a number without unit
+    // counts as seconds, so times in one scale are rescaled to another by the ratio of their ticks per unit.
+    pub fn ticks_per_unit(self) -> usize {
+        match self {
+            TimeScale::Units => 1,
+            TimeScale::Thousandths | TimeScale::Milliseconds => 1000,
+        }
+    }
+
+    // the length of a tick for the JSON output, which gives all times in ticks
+    pub fn tick(self) -> Option<&'static str> {
+        match self {
+            TimeScale::Units => None,
+            TimeScale::Thousandths => Some("0.001"),
+            TimeScale::Milliseconds => Some("1ms"),
+        }
+    }
+
+    // 4, 1.5 or 1h30m
+    pub fn format(self, ticks: TimeMoment) -> String {
+        match self {
+            TimeScale::Units => ticks.to_string(),
+            TimeScale::Thousandths => {
+                let fraction = format!("{:03}", ticks % 1000);
+                let fraction = fraction.trim_end_matches('0');
+                if fraction.is_empty() { (ticks / 1000).to_string() } else { format!("{}.{}", ticks / 1000, fraction) }
+            }
+            TimeScale::Milliseconds => {
+                let parts: String = [
+                    (MILLISECONDS_PER_DAY, "d"),
+                    (MILLISECONDS_PER_HOUR, "h"),
+                    (MILLISECONDS_PER_MINUTE, "m"),
+                    (MILLISECONDS_PER_SECOND, "s"),
+                    (1, "ms"),
+                ]
+                    .iter()
+                    .scan(ticks, |remaining, &(unit, name)| {
+                        let amount = *remaining / unit;
+                        *remaining %= unit;
+                        Some(if amount > 0 { format!("{}{}", amount, name) } else { String::new() })
+                    })
+                    .collect();
+                if parts.is_empty() { "0s".to_string() } else { parts }
+            }
+        }
+    }
+
+    pub fn format_signed(self, ticks: isize) -> String {
+        format!("{}{}", if ticks < 0 { "-" } else { "+" }, self.format(ticks.unsigned_abs()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_per_unit() {
+        assert_eq!(TimeScale::Units.ticks_per_unit(), 1);
+        assert_eq!(TimeScale::Thousandths.ticks_per_unit(), TimeScale::Milliseconds.ticks_per_unit());
+    }
+
+    #[test]
+    fn format_units() {
+        assert_eq!(TimeScale::Units.format(4), "4");
+    }
+
+    #[test]
+    fn format_thousandths() {
+        assert_eq!(TimeScale::Thousandths.format(1500), "1.5");
+        assert_eq!(TimeScale::Thousandths.format(2000), "2");
+        assert_eq!(TimeScale::Thousandths.format(250), "0.25");
+    }
+
+    #[test]
+    fn format_milliseconds() {
+        assert_eq!(TimeScale::Milliseconds.format(0), "0s");
+        assert_eq!(TimeScale::Milliseconds.format(250), "250ms");
+        assert_eq!(TimeScale::Milliseconds.format(90_000), "1m30s");
+        assert_eq!(TimeScale::Milliseconds.format(5_400_000), "1h30m");
+        assert_eq!(TimeScale::Milliseconds.format(MILLISECONDS_PER_DAY + 1500), "1d1s500ms");
+        assert_eq!(TimeScale::Milliseconds.format_signed(-2000), "-2s");
+    }
+}
@@ -1,3 +1,6 @@
+// the original tests pass the file name constants by reference
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 use std::fs;
 use assert_cmd::prelude::*;
 use predicates::prelude::*;
@@ -19,17 +22,38 @@ fn input_file_does_not_exist() -> Result<(), Box<dyn std::error::Error>> {
 #[test]
 fn make_schedule_for_input_file() -> Result<(), Box<dyn std::error::Error>> {
     const INPUT_FILE_NAME: &str = "./test/example.tasks.in";
-    const OUTPUT_FILE_NAME: &str = "test/example.sched.out";
     const CORRECT_OUTPUT: &str = "Critical: A->B->D->H
 Minimum: 4
-Parallelism: 3";
+Parallelism: 3
+";
 
     let cmd = Command::cargo_bin("schedule-tasks");
 
     cmd.expect("schedule-tasks binary not found")
         .arg(&INPUT_FILE_NAME)
         .assert()
-        .success();
+        .success()
+        .stdout(CORRECT_OUTPUT);
+
+    Ok(())
+}
+
+#[test]
+fn make_schedule_for_input_file_to_output_file() -> Result<(), Box<dyn std::error::Error>> {
+    const INPUT_FILE_NAME: &str = "./test/example.tasks.in";
+    const OUTPUT_FILE_NAME: &str = "test/example.sched.out";
+    const CORRECT_OUTPUT: &str = "Critical: A->B->D->H
+Minimum: 4
+Parallelism: 3
+";
+
+    let cmd = Command::cargo_bin("schedule-tasks");
+
+    cmd.expect("schedule-tasks binary not found")
+        .args([INPUT_FILE_NAME, "--output", OUTPUT_FILE_NAME])
+        .assert()
+        .success()
+        .stdout("");
 
     let output_file_content = fs::read_to_string(&OUTPUT_FILE_NAME).expect("Can not read output-file");
     assert_eq!(output_file_content, CORRECT_OUTPUT);
@@ -38,3 +62,873 @@ Parallelism: 3";
 
     Ok(())
 }
+
+#[test]
+fn make_schedule_for_input_from_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let input = fs::read_to_string("test/example.tasks.in")?;
+    let mut cmd = assert_cmd::Command::cargo_bin("schedule-tasks")?;
+
+    cmd.arg("-")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("Critical: A->B->D->H\nMinimum: 4\nParallelism: 3\n");
+
+    Ok(())
+}
+
+#[test]
+fn make_schedule_for_large_input_from_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    // a chain of 50.000 tasks, each also depending on the task 100 before it
+    let input: String = (0..50_000usize)
+        .map(|index| match index {
+            0 => "T0(1)\n".to_string(),
+            _ => format!("T{}(1) after [T{}, T{}]\n", index, index - 1, index.saturating_sub(100)),
+        })
+        .collect();
+    let mut cmd = assert_cmd::Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["-", "--machines", "4"])
+        .write_stdin(input)
+        .timeout(std::time::Duration::from_secs(60))
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("Minimum: 50000\n"));
+
+    Ok(())
+}
+
+#[test]
+fn make_schedule_for_input_file_outside_test_directory() -> Result<(), Box<dyn std::error::Error>> {
+    let input_file_name = std::env::temp_dir().join(format!("schedule-tasks-{}.job", std::process::id()));
+    let output_file_name = std::env::temp_dir().join(format!("schedule-tasks-{}.txt", std::process::id()));
+    fs::write(&input_file_name, "A(2)\nB(3)\n")?;
+
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.arg(&input_file_name)
+        .arg("-o")
+        .arg(&output_file_name)
+        .assert()
+        .success();
+
+    let output_file_content = fs::read_to_string(&output_file_name).expect("Can not read output-file");
+    assert_eq!(output_file_content, "Critical: B\nMinimum: 3\nParallelism: 2\n");
+
+    fs::remove_file(&input_file_name)?;
+    fs::remove_file(&output_file_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn make_schedule_for_input_file_on_fixed_number_of_machines() -> Result<(), Box<dyn std::error::Error>> {
+    const INPUT_FILE_NAME: &str = "./test/example.tasks.in";
+    const CORRECT_OUTPUT: &str = "Minimum: 5
+Machine 1: A[0-1] B[1-2] D[2-3] G[3-4] I[4-5] (idle: 0)
+Machine 2: C[1-2] F[2-3] H[3-4] (idle: 2)
+";
+
+    let cmd = Command::cargo_bin("schedule-tasks");
+
+    cmd.expect("schedule-tasks binary not found")
+        .args([INPUT_FILE_NAME, "--machines", "2"])
+        .assert()
+        .success()
+        .stdout(CORRECT_OUTPUT);
+
+    Ok(())
+}
+
+#[test]
+fn invalid_number_of_machines() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["./test/example.tasks.in", "--machines", "0"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Number of machines should be a positive integer"));
+
+    Ok(())
+}
+
+#[test]
+fn input_file_with_duplicate_task_name() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.arg("test/duplicate_task_name.tasks.in");
+    cmd.assert()
+        .failure()
+        .stderr("Error: task name A is not unique (line 1, column 1; line 3, column 1)\n");
+
+    Ok(())
+}
+
+#[test]
+fn input_file_with_unknown_dependency() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.arg("test/unknown_dependency.tasks.in");
+    cmd.assert()
+        .failure()
+        .stderr("Error: task B depends on unknown task C (line 2, column 13)\n");
+
+    Ok(())
+}
+
+#[test]
+fn input_file_with_dependency_cycle() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.arg("test/cycle.tasks.in");
+    cmd.assert()
+        .failure()
+        .stderr("Error: dependency cycle A -> B -> A (line 1, column 1; line 2, column 1)\n");
+
+    Ok(())
+}
+
+#[test]
+fn make_json_schedule_for_input_file() -> Result<(), Box<dyn std::error::Error>> {
+    const INPUT_FILE_NAME: &str = "./test/example.tasks.in";
+
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+    let assert = cmd.args([INPUT_FILE_NAME, "--format", "json"])
+        .assert()
+        .success();
+
+    let output: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout)?;
+    assert_eq!(output["critical_path"], serde_json::json!(["A", "B", "D", "H"]));
+    assert_eq!(output["makespan"], 4);
+    assert_eq!(output["max_parallelism"], 3);
+    assert_eq!(output["tasks"][0], serde_json::json!({
+        "name": "A", "duration": 1, "start_time": 0, "end_time": 1, "slack": 0, "earliest_start": 0, "earliest_finish": 1,
+        "latest_start": 0, "latest_finish": 1, "total_float": 0, "free_float": 0, "dependencies": []
+    }));
+
+    Ok(())
+}
+
+#[test]
+fn make_schedule_with_critical_path_method_figures() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["-", "--cpm"])
+        .write_stdin("A(3)\nB(1)\nC(1) after [B]\nD(1) after [A]\n")
+        .assert()
+        .success()
+        .stdout("Critical: A->D
+Minimum: 4
+Parallelism: 2
+
+Task  ES  EF  LS  LF  Total float  Free float
+A     0   3   0   3   0            0
+B     0   1   2   3   2            0
+C     1   2   3   4   2            2
+D     3   4   3   4   0            0
+");
+
+    Ok(())
+}
+
+#[test]
+fn make_schedule_with_all_critical_paths() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+    let assert = cmd.args(["./test/example.tasks.in", "--all-critical-paths", "--format", "json"])
+        .assert()
+        .success();
+
+    let output: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout)?;
+    assert_eq!(output["critical_paths"], serde_json::json!([
+        ["A", "B", "D", "H"],
+        ["A", "B", "F", "H"],
+        ["A", "B", "F", "I"],
+        ["A", "C", "F", "H"],
+        ["A", "C", "F", "I"],
+        ["A", "C", "G", "I"]
+    ]));
+    assert_eq!(output["critical_edges"].as_array().map(Vec::len), Some(10));
+
+    Ok(())
+}
+
+#[test]
+fn make_schedule_with_gantt_chart() -> Result<(), Box<dyn std::error::Error>> {
+    let svg_file_name = std::env::temp_dir().join("schedule-tasks-gantt.svg");
+    let mut cmd = assert_cmd::Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["-", "--gantt", "--svg"])
+        .arg(&svg_file_name)
+        .write_stdin("A(1)\nB(1) after [A]\nC(3) after [A]\n")
+        .assert()
+        .success()
+        .stdout("Critical: A->C
+Minimum: 4
+Parallelism: 2
+
+A |#   |
+B | =  |
+C | ###|
+  +----+
+  0    4
+2 | *  |
+1 |****|
+
+# critical  = not critical  * tasks running in parallel
+");
+
+    let svg = fs::read_to_string(&svg_file_name).expect("Can not read SVG file");
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.contains("<title>C [1-4]</title>"));
+    fs::remove_file(&svg_file_name).expect("Can not delete the SVG file");
+
+    Ok(())
+}
+
+#[test]
+fn emit_dependency_graph_as_dot() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["./test/example.tasks.in", "--emit", "dot", "--dot-clusters"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("digraph job {"))
+        .stdout(predicate::str::contains("  subgraph cluster_3 {\n    label=\"start 3\";\n    \"H\" [label=\"H (1)\", color=red];\n    \"I\" [label=\"I (1)\", color=red];\n  }"))
+        .stdout(predicate::str::contains("  \"D\" -> \"H\" [color=red, penwidth=2];"));
+
+    Ok(())
+}
+
+#[test]
+fn emit_dot_conflicts_with_json_and_machines() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+    cmd.args(["./test/example.tasks.in", "--emit", "dot", "--format", "json"])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(predicate::str::contains("The argument '--emit dot' cannot be used with '--format json'"));
+
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+    cmd.args(["./test/example.tasks.in", "--dot-clusters", "--machines", "2"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("The argument '--dot-clusters' cannot be used with '--machines [<N>...]'"));
+
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+    cmd.args(["./test/estimates.tasks.in", "--emit", "dot", "--simulate", "10"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("The argument '--emit dot' cannot be used with '--simulate'"));
+
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+    cmd.args(["./test/example.tasks.in", "--emit", "schedule", "--format", "json"])
+        .assert()
+        .success();
+
+    Ok(())
+}
+
+#[test]
+fn make_schedule_with_parallelism_profile() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["./test/example.tasks.in", "--profile"])
+        .assert()
+        .success()
+        .stdout("Critical: A->B->D->H
+Minimum: 4
+Parallelism: 3
+
+From  To  Tasks
+0     1   1
+1     2   2
+2     3   3
+3     4   2
+");
+
+    Ok(())
+}
+
+#[test]
+fn make_schedule_within_resource_capacities() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.arg("./test/resources.tasks.in")
+        .assert()
+        .success()
+        .stdout("Critical: A->B->D
+Minimum: 4
+Parallelism: 2
+Resources: seat 1 of 1, slot 2 of 2
+");
+
+    Ok(())
+}
+
+#[test]
+fn input_file_with_time_beyond_milliseconds() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["-", "--error-format", "compat"])
+        .write_stdin("A(1)\nB(1.5ms) after [A]\n")
+        .assert()
+        .failure()
+        .stderr("Error: line 2, column 3, kind: time is more precise than a millisecond\n");
+
+    Ok(())
+}
+
+#[test]
+fn input_file_with_times_with_and_without_a_unit() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["-", "--error-format", "compat"])
+        .write_stdin("A(1)\nB(500ms) after [A]\n")
+        .assert()
+        .failure()
+        .stderr("Error: line 2, column 1, kind: times with and without a unit in one file\n");
+
+    Ok(())
+}
+
+#[test]
+fn input_file_with_duplicate_metadata() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["-", "--error-format", "compat"])
+        .write_stdin("name: Release\nowner: Ops\nowner: Build team\nA(1)\n")
+        .assert()
+        .failure()
+        .stderr("Error: line 3, column 1, kind: metadata owner is given more than once\n");
+
+    Ok(())
+}
+
+#[test]
+fn input_file_with_duplicate_capacity() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::Command::cargo_bin("schedule-tasks")?;
+
+    cmd.arg("-")
+        .write_stdin("capacity [seat:1, seat:2]\nA(1) uses [seat:1]\n")
+        .assert()
+        .failure()
+        .stderr("Error: capacity of seat is declared more than once (line 1, column 1)\n");
+
+    Ok(())
+}
+
+#[test]
+fn input_file_with_task_exceeding_resource_capacity() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::Command::cargo_bin("schedule-tasks")?;
+
+    cmd.arg("-")
+        .write_stdin("capacity [seat:1]\nA(1)\nB(1) uses [seat:2] after [A]\n")
+        .assert()
+        .failure()
+        .stderr("Error: task B uses 2 seat but the capacity is 1 (line 3, column 1)\n");
+
+    Ok(())
+}
+
+#[test]
+fn make_schedule_on_machines_with_priorities_and_policy() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["-", "--machines", "2", "--policy", "longest-processing-time"])
+        .write_stdin("A(1)\nB(2)\nC(3)\nD(1) priority 1\n")
+        .assert()
+        .success()
+        .stdout("Minimum: 4
+Machine 1: D[0-1] B[1-3] A[3-4] (idle: 0)
+Machine 2: C[0-3] (idle: 1)
+");
+
+    Ok(())
+}
+
+#[test]
+fn make_schedule_with_dependency_types_and_lags() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["./test/dependency_types.tasks.in", "--cpm"])
+        .assert()
+        .success()
+        .stdout("Critical: A->B->C
+Minimum: 5
+Parallelism: 4
+
+Task  ES  EF  LS  LF  Total float  Free float
+A     0   4   0   4   0            0
+B     1   4   1   4   0            0
+C     2   5   2   5   0            0
+D     3   4   4   5   1            1
+");
+
+    Ok(())
+}
+
+#[test]
+fn make_schedule_with_durations_in_units() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["./test/units.tasks.in", "--profile"])
+        .assert()
+        .success()
+        .stdout("Critical: compile->test->package
+Minimum: 2h15m30s
+Parallelism: 2
+
+From    To        Tasks
+0s      10m       1
+10m     11m30s    2
+11m30s  1h30m     1
+1h30m   2h15m     1
+2h15m   2h15m30s  1
+");
+
+    Ok(())
+}
+
+#[test]
+fn simulate_schedule_with_three_point_estimates() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["./test/estimates.tasks.in", "--simulate", "1000", "--seed", "42"])
+        .assert()
+        .success()
+        .stdout("Runs: 1000 (pert, seed 42)
+P50: 11.26
+P90: 14.07
+P99: 16.254
+
+Task     Critical
+design   100.0%
+build    38.4%
+docs     61.7%
+release  100.0%
+");
+
+    Ok(())
+}
+
+#[test]
+fn reject_simulation_arguments_without_simulate() -> Result<(), Box<dyn std::error::Error>> {
+    for args in [["--seed", "5"], ["--distribution", "triangular"]] {
+        let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+        cmd.arg("./test/estimates.tasks.in")
+            .args(args)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("--simulate"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn make_schedule_with_comments_and_metadata() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.arg("./test/metadata.tasks.in")
+        .assert()
+        .success()
+        .stdout("Job: Nightly build
+Owner: Platform team
+Description: Compiles, tests and packages the shop
+Critical: compile->test
+Minimum: 15
+Parallelism: 2
+");
+
+    Ok(())
+}
+
+#[test]
+fn make_schedule_for_input_file_with_included_files() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.arg("./test/include/release.tasks.in")
+        .assert()
+        .success()
+        .stdout("Job: Release 1.2
+Critical: build::compile->docs::manual->release
+Minimum: 8
+Parallelism: 2
+");
+
+    Ok(())
+}
+
+#[test]
+fn included_file_with_unknown_dependency() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.arg("test/include/unknown_dependency.tasks.in")
+        .assert()
+        .failure()
+        .stderr("Error: task broken::check depends on unknown task build::lint (test/include/broken.tasks.in line 2, column 17)\n");
+
+    Ok(())
+}
+
+#[test]
+fn included_file_with_invalid_syntax() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["test/include/invalid_syntax.tasks.in", "--error-format", "compat"])
+        .assert()
+        .failure()
+        .stderr("Error: test/include/../invalidsyntax.tasks.in line 2, column 6, kind: expected \"after\"\n");
+
+    Ok(())
+}
+
+#[test]
+fn included_files_with_invalid_syntax_all_reported() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["test/include/syntax_errors_in_two_files.tasks.in", "--error-format", "compat"])
+        .assert()
+        .failure()
+        .stderr("Error: line 2, column 12, kind: expected \"after\"
+Error: test/include/../invalidsyntax.tasks.in line 2, column 6, kind: expected \"after\"
+");
+
+    Ok(())
+}
+
+#[test]
+fn input_file_with_syntax_and_job_errors() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["-", "--error-format", "compat"])
+        .write_stdin("A(1) after [C]\nB(1) afer [A]\nC(1) after [A]\nD(1) after [D]\nE(1) after [X, B]\n")
+        .assert()
+        .failure()
+        .stderr("Error: line 2, column 6, kind: expected \"after\"
+Error: task D depends on itself (line 4, column 13)
+Error: task E depends on unknown task X (line 5, column 13)
+Error: dependency cycle A -> C -> A (line 1, column 1; line 3, column 1)
+");
+
+    Ok(())
+}
+
+#[test]
+fn input_file_with_include_cycle() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.arg("test/include_cycle/a.tasks.in")
+        .assert()
+        .failure()
+        .stderr("Error: include cycle test/include_cycle/a.tasks.in -> test/include_cycle/b.tasks.in -> test/include_cycle/a.tasks.in (test/include_cycle/b.tasks.in line 1, column 1)\n");
+
+    Ok(())
+}
+
+#[test]
+fn input_file_with_invalid_syntax() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.arg("test/invalidsyntax.tasks.in")
+        .assert()
+        .failure()
+        .stderr(r#"error: expected "after" in the dependencies
+ --> test/invalidsyntax.tasks.in:2:6
+  |
+2 | B(1) afer [A]
+  |      ^^^^
+  = help: did you mean `after`?
+"#);
+
+    Ok(())
+}
+
+#[test]
+fn input_file_with_invalid_syntax_in_compatible_form() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["test/invalidsyntax.tasks.in", "--error-format", "compat"])
+        .assert()
+        .failure()
+        .stderr("Error: line 2, column 6, kind: expected \"after\"\n");
+
+    Ok(())
+}
+
+#[test]
+fn input_file_with_several_syntax_errors() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["test/syntax_errors.tasks.in", "--error-format", "compat"])
+        .assert()
+        .failure()
+        .stderr(r#"Error: line 2, column 6, kind: expected "after"
+Error: line 4, column 1, kind: expected ']'
+Error: line 4, column 1, kind: expected ','
+Error: line 5, column 4, kind: expected ')'
+"#);
+
+    Ok(())
+}
+
+#[test]
+fn input_file_with_several_job_errors() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.arg("test/job_errors.tasks.in")
+        .assert()
+        .failure()
+        .stderr("Error: task name A is not unique (line 1, column 1; line 4, column 1)
+Error: task C depends on unknown task X (line 3, column 13)
+Error: task C depends on itself (line 3, column 16)
+Error: dependency cycle A -> B -> A (line 1, column 1; line 2, column 1)
+");
+
+    Ok(())
+}
+
+#[test]
+fn watch_input_file_and_print_changes() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    const INPUT_FILE_NAME: &str = "test/watch.tasks.in";
+    const CORRECT_OUTPUT: &str = "Critical: A->B->D->H
+Minimum: 4
+Parallelism: 3
+
+Rescheduled 5 of 8 tasks
+Changed: B duration 1 -> 2
+Moved: D 2 -> 3 (+1) (critical)
+Moved: F 2 -> 3 (+1)
+Moved: H 3 -> 4 (+1) (critical)
+Moved: I 3 -> 4 (+1)
+Minimum: 4 -> 5
+Parallelism: 3 -> 2
+Critical: A->B->D->H
+";
+    let input = fs::read_to_string("test/example.tasks.in")?;
+    fs::write(INPUT_FILE_NAME, &input)?;
+
+    let mut child = Command::cargo_bin("schedule-tasks")?
+        .args([INPUT_FILE_NAME, "--watch"])
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let (sender, receiver) = mpsc::channel();
+    let stdout = child.stdout.take().expect("Can not read the output");
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            if sender.send(line.unwrap()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut output = String::new();
+    let mut read_until = |last_line: &str| -> Result<(), mpsc::RecvTimeoutError> {
+        loop {
+            let line = receiver.recv_timeout(Duration::from_secs(10))?;
+            output.push_str(&line);
+            output.push('\n');
+            if line.starts_with(last_line) {
+                return Ok(());
+            }
+        }
+    };
+    read_until("Parallelism")?;
+    // a modification time within the same second as the first one could go unnoticed on some file systems
+    thread::sleep(Duration::from_millis(1100));
+    fs::write(INPUT_FILE_NAME, input.replace("B(1)", "B(2)"))?;
+    let result = read_until("Critical");
+
+    child.kill()?;
+    fs::remove_file(INPUT_FILE_NAME)?;
+    result?;
+    assert_eq!(output, CORRECT_OUTPUT);
+
+    Ok(())
+}
+
+#[test]
+fn watch_with_json_output() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["test/example.tasks.in", "--watch", "--format", "json"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("The argument '--watch' cannot be used with '--format json'"));
+
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+    cmd.args(["test/example.tasks.in", "--watch", "--output", "schedule.out"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+
+    Ok(())
+}
+
+#[test]
+fn watch_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["-", "--watch"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Cannot watch stdin"));
+
+    Ok(())
+}
+
+#[test]
+fn diff_schedules_of_two_input_files() -> Result<(), Box<dyn std::error::Error>> {
+    const CORRECT_OUTPUT: &str = "Added: changelog
+Removed: docs
+Changed: test duration 2 -> 4
+Changed: package dependencies [test, docs] -> [test, lint, changelog]
+Moved: package 5 -> 7 (+2) (critical)
+Minimum: 6 -> 8
+Parallelism: 2
+Critical: compile->test->package
+";
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["diff", "test/diff/old.tasks.in", "test/diff/new.tasks.in"]);
+    cmd.assert()
+        .success()
+        .stdout(CORRECT_OUTPUT);
+
+    Ok(())
+}
+
+#[test]
+fn diff_schedules_of_two_input_files_as_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["diff", "test/diff/old.tasks.in", "test/diff/new.tasks.in", "--format", "json"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(r#""makespan": [
+    6,
+    8
+  ],"#));
+
+    Ok(())
+}
+
+#[test]
+fn diff_schedules_with_a_policy() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["diff", "test/diff/old_resources.tasks.in", "test/diff/new_resources.tasks.in", "--policy", "longest-processing-time"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Moved: A 3 -> 2 (-1) (critical)"));
+
+    Ok(())
+}
+
+#[test]
+fn input_file_named_like_a_subcommand() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.current_dir("test/subcommand_name").arg("./diff");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Critical: compile->test->package"));
+
+    Ok(())
+}
+
+#[test]
+fn diff_with_invalid_input_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["diff", "test/diff/old.tasks.in", "test/cycle.tasks.in"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Error: "));
+
+    Ok(())
+}
+
+#[test]
+fn execute_the_commands_of_the_tasks() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["exec", "test/exec/build.tasks.in", "--jobs", "2"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("Job: Build
+Workers: 2
+Succeeded: 4, failed: 0, skipped: 0
+Planned: 3s, actual: "))
+        .stdout(predicate::str::contains("test     succeeded  2s"))
+        .stderr(predicate::str::contains("lib: compiling lib"));
+
+    Ok(())
+}
+
+#[test]
+fn execute_a_command_with_escaped_quotes() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["exec", "test/exec/escaped.tasks.in"]);
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains(r#"greet: say "hello" \ bye"#));
+
+    Ok(())
+}
+
+#[test]
+fn execute_and_skip_the_dependents_of_a_failed_task() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+
+    cmd.args(["exec", "test/exec/failure.tasks.in", "--jobs", "1", "--format", "json"]);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains(r#""name": "package",
+      "command": "echo packaging",
+      "status": "skipped",
+      "message": "test failed",
+      "planned_start": 3000,
+      "planned_duration": 1000,
+      "actual_start": null,
+      "actual_duration": null"#))
+        .stdout(predicate::str::contains(r#""message": "exit status: 2""#))
+        .stderr(predicate::str::contains("test: 2 tests failed"));
+
+    Ok(())
+}
+
+#[test]
+fn resume_an_execution_from_the_state_file() -> Result<(), Box<dyn std::error::Error>> {
+    let state_file_name = std::env::temp_dir().join(format!("schedule-tasks-state-{}.json", std::process::id()));
+
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+    cmd.args(["exec", "test/exec/failure.tasks.in", "--jobs", "1", "--state"]).arg(&state_file_name);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("lib: compiling lib"));
+
+    // lib and docs succeeded in the first run, the failed test runs again
+    let mut cmd = Command::cargo_bin("schedule-tasks")?;
+    cmd.args(["exec", "test/exec/failure.tasks.in", "--jobs", "1", "--state"]).arg(&state_file_name);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("Succeeded: 0, failed: 1, skipped: 1, reused: 2"))
+        .stderr(predicate::str::contains("test: 2 tests failed"))
+        .stderr(predicate::str::contains("lib: compiling lib").not());
+
+    let state = fs::read_to_string(&state_file_name)?;
+    fs::remove_file(&state_file_name)?;
+    assert!(state.contains(r#""name": "test","#));
+    assert!(state.contains(r#""status": "failed","#));
+
+    Ok(())
+}